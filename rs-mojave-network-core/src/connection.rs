@@ -9,4 +9,21 @@ pub enum ConnectionOrigin {
 		local_addr: Multiaddr,
 		remote_addr: Multiaddr,
 	},
+	/// The connection was established via simultaneous open: both peers dialed each other at
+	/// once (e.g. while hole punching) and a single initiator was picked by the
+	/// `/libp2p/simultaneous-connect` role-arbitration handshake. Tracked separately from
+	/// [`ConnectionOrigin::Dialer`]/[`ConnectionOrigin::Listener`] so that the connection, which
+	/// would otherwise look like both an outgoing and an incoming attempt, isn't double-counted.
+	SimultaneousOpen {
+		local_addr: Multiaddr,
+		remote_addr: Multiaddr,
+	},
+}
+
+impl ConnectionOrigin {
+	/// Returns `true` if this connection was established by simultaneous-open role arbitration
+	/// rather than a plain dial or accepted inbound connection.
+	pub fn is_simultaneous_open(&self) -> bool {
+		matches!(self, ConnectionOrigin::SimultaneousOpen { .. })
+	}
 }
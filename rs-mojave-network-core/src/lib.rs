@@ -3,7 +3,7 @@ pub mod muxing;
 pub mod transport;
 
 pub use muxing::StreamMuxer;
-pub use transport::Transport;
+pub use transport::{Protocol, Transport};
 
 pub mod util {
 	use std::convert::Infallible;
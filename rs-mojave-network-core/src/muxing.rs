@@ -0,0 +1,127 @@
+use std::{
+	error::Error,
+	io,
+	pin::Pin,
+	task::{Context, Poll},
+};
+
+use futures::{AsyncRead, AsyncWrite};
+
+/// Multiplexes a single underlying connection into independent, ordered byte streams.
+///
+/// Mirrors [`crate::transport::Transport`]'s shape: an associated `Error` instead of a
+/// hard-coded [`io::Error`], so a concrete muxer implementation (WebTransport session resets,
+/// stream-limit exhaustion, ...) can be distinguished by the caller instead of being collapsed
+/// into an opaque I/O error.
+pub trait StreamMuxer: Unpin {
+	/// A single substream opened over this muxed connection.
+	type Substream: AsyncRead + AsyncWrite + Unpin;
+
+	/// The error type for this muxer's substream and muxer-level operations.
+	type Error: Error + Send + Sync + 'static;
+
+	/// Polls for a new inbound substream opened by the remote.
+	fn poll_inbound(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Self::Substream, Self::Error>>;
+
+	/// Opens a new outbound substream.
+	fn poll_outbound(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Self::Substream, Self::Error>>;
+
+	/// Closes the muxed connection, and all of its substreams.
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>>;
+}
+
+/// A type-erased [`StreamMuxer::Substream`]: a boxed, dynamically dispatched byte stream.
+pub type BoxedSubstream = Box<dyn AsyncReadWrite + Send + Unpin>;
+
+/// Helper bound so [`BoxedSubstream`] can require both halves of the stream without a second
+/// trait object.
+pub trait AsyncReadWrite: AsyncRead + AsyncWrite {}
+impl<T: AsyncRead + AsyncWrite> AsyncReadWrite for T {}
+
+/// A type-erased [`StreamMuxer`], boxed so transports with different concrete muxer types can be
+/// stored and driven uniformly. Concrete muxer errors are preserved (rather than collapsed into
+/// an opaque [`io::Error`]) via [`io::Error::other`], whose wrapped error is recoverable with
+/// [`io::Error::into_inner`] followed by a downcast.
+pub struct StreamMuxerBox {
+	inner: Box<dyn Abstract + Send + Unpin>,
+}
+
+type PollResult<T> = Poll<Result<T, io::Error>>;
+
+trait Abstract {
+	fn poll_inbound(self: Pin<&mut Self>, cx: &mut Context<'_>) -> PollResult<BoxedSubstream>;
+	fn poll_outbound(self: Pin<&mut Self>, cx: &mut Context<'_>) -> PollResult<BoxedSubstream>;
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> PollResult<()>;
+}
+
+impl<T> Abstract for T
+where
+	T: StreamMuxer,
+	T::Substream: Send + 'static,
+{
+	fn poll_inbound(self: Pin<&mut Self>, cx: &mut Context<'_>) -> PollResult<BoxedSubstream> {
+		match StreamMuxer::poll_inbound(self, cx) {
+			Poll::Ready(Ok(s)) => Poll::Ready(Ok(Box::new(s) as BoxedSubstream)),
+			Poll::Ready(Err(e)) => Poll::Ready(Err(box_err(e))),
+			Poll::Pending => Poll::Pending,
+		}
+	}
+
+	fn poll_outbound(self: Pin<&mut Self>, cx: &mut Context<'_>) -> PollResult<BoxedSubstream> {
+		match StreamMuxer::poll_outbound(self, cx) {
+			Poll::Ready(Ok(s)) => Poll::Ready(Ok(Box::new(s) as BoxedSubstream)),
+			Poll::Ready(Err(e)) => Poll::Ready(Err(box_err(e))),
+			Poll::Pending => Poll::Pending,
+		}
+	}
+
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> PollResult<()> {
+		match StreamMuxer::poll_close(self, cx) {
+			Poll::Ready(Ok(())) => Poll::Ready(Ok(())),
+			Poll::Ready(Err(e)) => Poll::Ready(Err(box_err(e))),
+			Poll::Pending => Poll::Pending,
+		}
+	}
+}
+
+impl StreamMuxerBox {
+	/// Boxes `muxer`, type-erasing its concrete [`StreamMuxer::Substream`]/[`StreamMuxer::Error`]
+	/// while keeping the original error recoverable (see [`StreamMuxerBox`]'s docs).
+	pub fn new<T>(muxer: T) -> Self
+	where
+		T: StreamMuxer + Send + 'static,
+		T::Substream: Send + 'static,
+	{
+		Self { inner: Box::new(muxer) }
+	}
+
+	pub fn poll_inbound(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<BoxedSubstream>> {
+		Pin::new(self.inner.as_mut()).poll_inbound(cx)
+	}
+
+	pub fn poll_outbound(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<BoxedSubstream>> {
+		Pin::new(self.inner.as_mut()).poll_outbound(cx)
+	}
+
+	pub fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Pin::new(self.inner.as_mut()).poll_close(cx)
+	}
+}
+
+/// Extension methods for boxing a concrete [`StreamMuxer`] into a [`StreamMuxerBox`].
+pub trait StreamMuxerExt: StreamMuxer + Sized {
+	/// Boxes `self` into a [`StreamMuxerBox`].
+	fn boxed(self) -> StreamMuxerBox
+	where
+		Self: Send + 'static,
+		Self::Substream: Send + 'static,
+	{
+		StreamMuxerBox::new(self)
+	}
+}
+
+impl<T: StreamMuxer> StreamMuxerExt for T {}
+
+fn box_err<E: Error + Send + Sync + 'static>(e: E) -> io::Error {
+	io::Error::other(e)
+}
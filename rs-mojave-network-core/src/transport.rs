@@ -0,0 +1,198 @@
+use std::{
+	error::Error,
+	pin::Pin,
+	task::{Context, Poll},
+};
+
+use multiaddr::Multiaddr;
+
+mod and_then;
+mod boxed;
+mod map;
+mod map_err;
+mod or;
+
+pub use and_then::AndThen;
+pub use boxed::Boxed;
+pub use map::Map;
+pub use map_err::MapErr;
+pub use or::OrTransport;
+
+/// The multiaddr protocol family a [`Transport`] dials and listens on, used by
+/// [`crate::transport`]'s callers (e.g. `rs-mojave-transport-node`'s `Builder`) to route a
+/// [`Multiaddr`] to the transport registered for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+	WebTransport,
+}
+
+/// An error dialing or listening on a [`Multiaddr`], or produced by an in-flight
+/// [`Transport::Dial`]/[`Transport::ListenerUpgrade`] future.
+#[derive(Debug, thiserror::Error)]
+pub enum TransportError<TErr> {
+	/// The [`Multiaddr`] isn't one this transport knows how to dial/listen on.
+	#[error("multiaddr not supported: {0}")]
+	MultiaddrNotSupported(Multiaddr),
+
+	/// Any other, transport-specific error.
+	#[error("{0}")]
+	Other(TErr),
+}
+
+impl<TErr> TransportError<TErr> {
+	/// Maps the wrapped [`TransportError::Other`] error, leaving
+	/// [`TransportError::MultiaddrNotSupported`] untouched.
+	pub fn map<F, TNewErr>(self, f: F) -> TransportError<TNewErr>
+	where
+		F: FnOnce(TErr) -> TNewErr,
+	{
+		match self {
+			TransportError::MultiaddrNotSupported(addr) => TransportError::MultiaddrNotSupported(addr),
+			TransportError::Other(err) => TransportError::Other(f(err)),
+		}
+	}
+}
+
+/// An event produced by polling a [`Transport`]'s listeners.
+#[derive(Debug)]
+pub enum TransportEvent<TUpgr, TErr> {
+	/// An incoming connection, not yet upgraded into `Transport::Output`.
+	Incoming {
+		upgrade: TUpgr,
+		local_addr: Multiaddr,
+		remote_addr: Multiaddr,
+	},
+	/// The transport started listening on a new address.
+	ListenAddress { address: Multiaddr },
+	/// A previously reported listen address is no longer valid.
+	AddressExpired { address: Multiaddr },
+	/// A listener closed, gracefully or with an error.
+	ListenerClosed { reason: Result<(), TErr> },
+	/// A listener reported an error without closing.
+	ListenerError { error: TErr },
+}
+
+impl<TUpgr, TErr> TransportEvent<TUpgr, TErr> {
+	/// Maps the upgrade future carried by [`TransportEvent::Incoming`], leaving every other
+	/// variant untouched.
+	pub fn map_upgrade<F, TNewUpgr>(self, f: F) -> TransportEvent<TNewUpgr, TErr>
+	where
+		F: FnOnce(TUpgr) -> TNewUpgr,
+	{
+		match self {
+			TransportEvent::Incoming {
+				upgrade,
+				local_addr,
+				remote_addr,
+			} => TransportEvent::Incoming {
+				upgrade: f(upgrade),
+				local_addr,
+				remote_addr,
+			},
+			TransportEvent::ListenAddress { address } => TransportEvent::ListenAddress { address },
+			TransportEvent::AddressExpired { address } => TransportEvent::AddressExpired { address },
+			TransportEvent::ListenerClosed { reason } => TransportEvent::ListenerClosed { reason },
+			TransportEvent::ListenerError { error } => TransportEvent::ListenerError { error },
+		}
+	}
+
+	/// Maps this event's error type, carried by [`TransportEvent::ListenerClosed`] and
+	/// [`TransportEvent::ListenerError`].
+	pub fn map_err<F, TNewErr>(self, f: F) -> TransportEvent<TUpgr, TNewErr>
+	where
+		F: FnOnce(TErr) -> TNewErr,
+	{
+		match self {
+			TransportEvent::Incoming {
+				upgrade,
+				local_addr,
+				remote_addr,
+			} => TransportEvent::Incoming {
+				upgrade,
+				local_addr,
+				remote_addr,
+			},
+			TransportEvent::ListenAddress { address } => TransportEvent::ListenAddress { address },
+			TransportEvent::AddressExpired { address } => TransportEvent::AddressExpired { address },
+			TransportEvent::ListenerClosed { reason } => TransportEvent::ListenerClosed { reason: reason.map_err(f) },
+			TransportEvent::ListenerError { error } => TransportEvent::ListenerError { error: f(error) },
+		}
+	}
+}
+
+/// Dials and listens on [`Multiaddr`]s for a single [`Protocol`] family, producing `Output` (e.g.
+/// a `(PeerId, StreamMuxerBox)` pair) per connection.
+///
+/// Concrete transports are combined and type-erased for storage via [`TransportExt`]'s
+/// `.map`/`.and_then`/`.map_err`/`.boxed` adapters, mirroring [`crate::muxing::StreamMuxer`]'s
+/// boxing story.
+pub trait Transport {
+	/// What a successfully dialed or accepted connection resolves to.
+	type Output;
+
+	/// This transport's error type.
+	type Error: Error;
+
+	/// The future produced by [`Transport::dial`].
+	type Dial: Future<Output = Result<Self::Output, Self::Error>>;
+
+	/// The future produced for an incoming connection, see [`TransportEvent::Incoming`].
+	type ListenerUpgrade: Future<Output = Result<Self::Output, Self::Error>>;
+
+	/// The [`Protocol`] family this transport dials/listens on.
+	fn supported_protocols_for_dialing(&self) -> Protocol;
+
+	/// Starts listening on `addr`. Repeated calls accumulate listeners rather than replacing one
+	/// another.
+	fn listen_on(&mut self, addr: Multiaddr) -> Result<(), TransportError<Self::Error>>;
+
+	/// Starts dialing `addr`, returning a future that resolves once the connection is
+	/// established.
+	fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>>;
+
+	/// Polls this transport's listeners for the next [`TransportEvent`].
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>>;
+}
+
+/// Combinators for building a [`Transport`] up from simpler pieces before boxing it for storage
+/// (e.g. via `rs-mojave-transport-node`'s `Builder::with_transport`).
+pub trait TransportExt: Transport + Sized {
+	/// Type-erases this transport into a [`Boxed`].
+	fn boxed(self) -> Boxed<Self::Output>
+	where
+		Self: Send + Unpin + 'static,
+		Self::Error: Send + Sync,
+		Self::Dial: Send + 'static,
+		Self::ListenerUpgrade: Send + 'static,
+	{
+		boxed::boxed(self)
+	}
+
+	/// Transforms this transport's `Output`, e.g. to run an upgrade step (security/muxing) after
+	/// dialing/accepting a raw connection.
+	fn map<F, TOut>(self, f: F) -> Map<Self, F>
+	where
+		F: FnOnce(Self::Output) -> TOut + Clone,
+	{
+		Map::new(self, f)
+	}
+
+	/// Chains an async upgrade step onto this transport's `Output`.
+	fn and_then<F, TFut, TOut>(self, f: F) -> AndThen<Self, F>
+	where
+		F: FnOnce(Self::Output) -> TFut + Clone,
+		TFut: Future<Output = Result<TOut, Self::Error>>,
+	{
+		AndThen::new(self, f)
+	}
+
+	/// Transforms this transport's error type.
+	fn map_err<F, TNewErr>(self, f: F) -> MapErr<Self, F>
+	where
+		F: FnOnce(Self::Error) -> TNewErr + Clone,
+	{
+		MapErr::new(self, f)
+	}
+}
+
+impl<T: Transport> TransportExt for T {}
@@ -0,0 +1,57 @@
+use std::{
+	pin::Pin,
+	task::{Context, Poll},
+};
+
+use futures::TryFutureExt;
+use multiaddr::Multiaddr;
+
+use crate::transport::{Protocol, Transport, TransportError, TransportEvent};
+
+/// A [`Transport`] whose `Output` has been transformed by [`crate::transport::TransportExt::map`].
+pub struct Map<T, F> {
+	transport: T,
+	f: F,
+}
+
+impl<T, F> Map<T, F> {
+	pub(crate) fn new(transport: T, f: F) -> Self {
+		Self { transport, f }
+	}
+}
+
+impl<T, F, TOut> Transport for Map<T, F>
+where
+	T: Transport,
+	F: FnOnce(T::Output) -> TOut + Clone + Send + 'static,
+	T::Dial: Send + 'static,
+	T::ListenerUpgrade: Send + 'static,
+{
+	type Output = TOut;
+	type Error = T::Error;
+	type Dial = Pin<Box<dyn Future<Output = Result<TOut, T::Error>> + Send>>;
+	type ListenerUpgrade = Pin<Box<dyn Future<Output = Result<TOut, T::Error>> + Send>>;
+
+	fn supported_protocols_for_dialing(&self) -> Protocol {
+		self.transport.supported_protocols_for_dialing()
+	}
+
+	fn listen_on(&mut self, addr: Multiaddr) -> Result<(), TransportError<Self::Error>> {
+		self.transport.listen_on(addr)
+	}
+
+	fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+		let f = self.f.clone();
+		let fut = self.transport.dial(addr)?;
+		Ok(Box::pin(fut.map_ok(f)))
+	}
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+		let this = self.get_mut();
+
+		Pin::new(&mut this.transport).poll(cx).map(|event| {
+			let f = this.f.clone();
+			event.map_upgrade(move |upgrade| Box::pin(upgrade.map_ok(f)) as Self::ListenerUpgrade)
+		})
+	}
+}
@@ -0,0 +1,63 @@
+use std::{
+	error::Error,
+	pin::Pin,
+	task::{Context, Poll},
+};
+
+use futures::TryFutureExt;
+use multiaddr::Multiaddr;
+
+use crate::transport::{Protocol, Transport, TransportError, TransportEvent};
+
+/// A [`Transport`] whose `Error` has been transformed by
+/// [`crate::transport::TransportExt::map_err`].
+pub struct MapErr<T, F> {
+	transport: T,
+	f: F,
+}
+
+impl<T, F> MapErr<T, F> {
+	pub(crate) fn new(transport: T, f: F) -> Self {
+		Self { transport, f }
+	}
+}
+
+impl<T, F, TNewErr> Transport for MapErr<T, F>
+where
+	T: Transport,
+	F: FnOnce(T::Error) -> TNewErr + Clone + Send + 'static,
+	TNewErr: Error,
+	T::Dial: Send + 'static,
+	T::ListenerUpgrade: Send + 'static,
+{
+	type Output = T::Output;
+	type Error = TNewErr;
+	type Dial = Pin<Box<dyn Future<Output = Result<T::Output, TNewErr>> + Send>>;
+	type ListenerUpgrade = Pin<Box<dyn Future<Output = Result<T::Output, TNewErr>> + Send>>;
+
+	fn supported_protocols_for_dialing(&self) -> Protocol {
+		self.transport.supported_protocols_for_dialing()
+	}
+
+	fn listen_on(&mut self, addr: Multiaddr) -> Result<(), TransportError<Self::Error>> {
+		let f = self.f.clone();
+		self.transport.listen_on(addr).map_err(|e| e.map(f))
+	}
+
+	fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+		let f = self.f.clone();
+		let fut = self.transport.dial(addr).map_err(|e| e.map(self.f.clone()))?;
+		Ok(Box::pin(fut.map_err(f)))
+	}
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+		let this = self.get_mut();
+
+		Pin::new(&mut this.transport).poll(cx).map(|event| {
+			let f = this.f.clone();
+			event
+				.map_upgrade(move |upgrade| Box::pin(upgrade.map_err(f)) as Self::ListenerUpgrade)
+				.map_err(this.f.clone())
+		})
+	}
+}
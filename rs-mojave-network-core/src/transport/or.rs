@@ -0,0 +1,86 @@
+use std::{
+	pin::Pin,
+	task::{Context, Poll},
+};
+
+use futures::{TryFutureExt, future::Either};
+use multiaddr::Multiaddr;
+
+use crate::transport::{Protocol, Transport, TransportError, TransportEvent};
+
+/// Tries `a`, falling back to `b` when `a` reports [`TransportError::MultiaddrNotSupported`].
+///
+/// Lets e.g. WebTransport and a direct QUIC transport share one multiaddr family, with QUIC
+/// picked up whenever WebTransport declines the address.
+pub struct OrTransport<A, B> {
+	a: A,
+	b: B,
+}
+
+impl<A, B> OrTransport<A, B> {
+	pub fn new(a: A, b: B) -> Self {
+		Self { a, b }
+	}
+}
+
+impl<A, B> Transport for OrTransport<A, B>
+where
+	A: Transport,
+	B: Transport,
+	A::Dial: Send + 'static,
+	B::Dial: Send + 'static,
+	A::ListenerUpgrade: Send + 'static,
+	B::ListenerUpgrade: Send + 'static,
+{
+	type Output = Either<A::Output, B::Output>;
+	type Error = Either<A::Error, B::Error>;
+	type Dial = Pin<Box<dyn Future<Output = Result<Self::Output, Self::Error>> + Send>>;
+	type ListenerUpgrade = Pin<Box<dyn Future<Output = Result<Self::Output, Self::Error>> + Send>>;
+
+	fn supported_protocols_for_dialing(&self) -> Protocol {
+		// `a` is tried first, so it determines which multiaddr family this combinator is keyed
+		// under (e.g. in `rs-mojave-transport-node`'s `Builder`, which dispatches by `Protocol`).
+		self.a.supported_protocols_for_dialing()
+	}
+
+	fn listen_on(&mut self, addr: Multiaddr) -> Result<(), TransportError<Self::Error>> {
+		match self.a.listen_on(addr.clone()) {
+			Ok(()) => Ok(()),
+			Err(TransportError::MultiaddrNotSupported(_)) => self.b.listen_on(addr).map_err(|e| e.map(Either::Right)),
+			Err(TransportError::Other(e)) => Err(TransportError::Other(Either::Left(e))),
+		}
+	}
+
+	fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+		match self.a.dial(addr.clone()) {
+			Ok(fut) => Ok(Box::pin(fut.map_ok(Either::Left).map_err(Either::Left))),
+			Err(TransportError::MultiaddrNotSupported(_)) => {
+				let fut = self.b.dial(addr).map_err(|e| e.map(Either::Right))?;
+				Ok(Box::pin(fut.map_ok(Either::Right).map_err(Either::Right)))
+			}
+			Err(TransportError::Other(e)) => Err(TransportError::Other(Either::Left(e))),
+		}
+	}
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+		let this = self.get_mut();
+
+		if let Poll::Ready(event) = Pin::new(&mut this.a).poll(cx) {
+			return Poll::Ready(
+				event
+					.map_upgrade(|upgrade| Box::pin(upgrade.map_ok(Either::Left).map_err(Either::Left)) as Self::ListenerUpgrade)
+					.map_err(Either::Left),
+			);
+		}
+
+		if let Poll::Ready(event) = Pin::new(&mut this.b).poll(cx) {
+			return Poll::Ready(
+				event
+					.map_upgrade(|upgrade| Box::pin(upgrade.map_ok(Either::Right).map_err(Either::Right)) as Self::ListenerUpgrade)
+					.map_err(Either::Right),
+			);
+		}
+
+		Poll::Pending
+	}
+}
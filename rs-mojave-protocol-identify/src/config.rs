@@ -0,0 +1,59 @@
+use multiaddr::Multiaddr;
+use rs_mojave_transport_node::StreamProtocol;
+
+/// The local information advertised to peers, kept up to date by the application as the node's
+/// protocol and address sets change (see [`crate::Protocol::update_listen_addrs`] and
+/// [`crate::Protocol::update_supported_protocols`]).
+#[derive(Debug, Clone)]
+pub struct Config {
+	/// A free-form version string identifying the application, e.g. `"rs-mojave/0.0.1"`.
+	protocol_version: String,
+	/// The [`StreamProtocol`]s supported by this node's active protocol handlers.
+	supported_protocols: Vec<StreamProtocol>,
+	/// The addresses this node is listening on.
+	listen_addrs: Vec<Multiaddr>,
+}
+
+impl Config {
+	/// Creates a new [`Config`] advertising `protocol_version`, with no supported protocols or
+	/// listen addresses set yet.
+	pub fn new(protocol_version: impl Into<String>) -> Self {
+		Self {
+			protocol_version: protocol_version.into(),
+			supported_protocols: Vec::new(),
+			listen_addrs: Vec::new(),
+		}
+	}
+
+	/// Sets the [`StreamProtocol`]s advertised as supported by this node.
+	pub fn with_supported_protocols(mut self, protocols: Vec<StreamProtocol>) -> Self {
+		self.supported_protocols = protocols;
+		self
+	}
+
+	/// Sets the addresses advertised as this node's listen addresses.
+	pub fn with_listen_addrs(mut self, addrs: Vec<Multiaddr>) -> Self {
+		self.listen_addrs = addrs;
+		self
+	}
+
+	pub fn protocol_version(&self) -> &str {
+		&self.protocol_version
+	}
+
+	pub fn supported_protocols(&self) -> &[StreamProtocol] {
+		&self.supported_protocols
+	}
+
+	pub fn listen_addrs(&self) -> &[Multiaddr] {
+		&self.listen_addrs
+	}
+
+	pub(crate) fn set_supported_protocols(&mut self, protocols: Vec<StreamProtocol>) {
+		self.supported_protocols = protocols;
+	}
+
+	pub(crate) fn set_listen_addrs(&mut self, addrs: Vec<Multiaddr>) {
+		self.listen_addrs = addrs;
+	}
+}
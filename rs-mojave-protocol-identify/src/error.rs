@@ -0,0 +1,7 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("unsupported protocol")]
+	UnsupportedProtocol,
+	#[error(transparent)]
+	Io(#[from] std::io::Error),
+}
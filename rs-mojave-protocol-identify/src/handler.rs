@@ -0,0 +1,175 @@
+use std::{collections::VecDeque, iter, task::Poll};
+
+use futures::{
+	StreamExt,
+	future::{BoxFuture, FutureExt},
+	stream::FuturesUnordered,
+};
+use multiaddr::Multiaddr;
+use rs_mojave_transport_node::{AsyncReadWrite, ConnectionEvent, ProtocolHandler, ProtocolHandlerEvent, ProtocolInfo, StreamProtocol};
+
+use crate::{Error, protocol, protocol::Info};
+
+type BoxedStream = Box<dyn AsyncReadWrite + Send + Unpin>;
+
+/// A command delivered to a single connection's [`Handler`] by the owning [`crate::Protocol`]:
+/// push a fresh [`Info`] snapshot out over a new substream.
+#[derive(Debug)]
+pub enum Command {
+	Push(Info),
+}
+
+/// The peer's advertised protocols and the address it observed us at, reported once per identify
+/// exchange.
+#[derive(Debug, Clone)]
+pub struct Received {
+	pub supported_protocols: Vec<StreamProtocol>,
+	pub observed_addr: Multiaddr,
+}
+
+pub struct Handler {
+	protocol_name: StreamProtocol,
+
+	/// [`Info`] snapshots queued to be pushed out, each over its own freshly opened substream, in
+	/// the order they were produced.
+	pending_pushes: VecDeque<Info>,
+	/// Number of [`ProtocolHandlerEvent::OutboundSubstreamRequest`]s we've emitted that haven't
+	/// been fulfilled by a matching [`ConnectionEvent::NewOutboundStream`] yet.
+	outbound_requested: usize,
+	/// Outbound substreams currently sending their queued push.
+	outbound: FuturesUnordered<BoxFuture<'static, std::io::Result<()>>>,
+
+	/// Inbound substreams currently reading the peer's pushed [`Info`], in arrival order.
+	inbound: FuturesUnordered<BoxFuture<'static, std::io::Result<Info>>>,
+
+	pending_events: VecDeque<Result<Received, Error>>,
+
+	/// Set once `poll_close` has been called; stops accepting new work.
+	closing: bool,
+}
+
+impl Handler {
+	/// Creates a handler that immediately queues `initial` to be pushed over the first outbound
+	/// substream it's given.
+	pub fn new(protocol_name: StreamProtocol, initial: Info) -> Self {
+		let mut pending_pushes = VecDeque::new();
+		pending_pushes.push_back(initial);
+
+		Self {
+			protocol_name,
+			pending_pushes,
+			outbound_requested: 0,
+			outbound: FuturesUnordered::new(),
+			inbound: FuturesUnordered::new(),
+			pending_events: VecDeque::new(),
+			closing: false,
+		}
+	}
+}
+
+impl ProtocolHandler for Handler {
+	type FromProtocol = Command;
+	type ToProtocol = Result<Received, Error>;
+	type ProtocolInfoIter = iter::Once<ProtocolInfo>;
+
+	fn protocol_info(&self) -> Self::ProtocolInfoIter {
+		iter::once(ProtocolInfo::Exact(self.protocol_name.clone()))
+	}
+
+	fn on_protocol_event(&mut self, event: Self::FromProtocol) {
+		match event {
+			Command::Push(info) => self.pending_pushes.push_back(info),
+		}
+	}
+
+	fn on_connection_event(&mut self, event: ConnectionEvent) {
+		match event {
+			ConnectionEvent::NewOutboundStream(_protocol, stream) => {
+				self.outbound_requested = self.outbound_requested.saturating_sub(1);
+				if let Some(info) = self.pending_pushes.pop_front() {
+					self.outbound.push(push(stream, info));
+				}
+			}
+			ConnectionEvent::NewInboundStream(_protocol, stream) => {
+				if self.closing {
+					// Draining: don't accept new work on a handler that's shutting down.
+					return;
+				}
+				self.inbound.push(protocol::recv_identify(stream).boxed());
+			}
+			ConnectionEvent::FailNegotiation(err) => {
+				let error = match err {
+					rs_mojave_transport_node::negotiator::NegotiatorStreamError::Timeout => {
+						Error::Io(std::io::Error::new(std::io::ErrorKind::TimedOut, "identify negotiation timed out"))
+					}
+					rs_mojave_transport_node::negotiator::NegotiatorStreamError::IoError(error) => Error::Io(error),
+					rs_mojave_transport_node::negotiator::NegotiatorStreamError::NegotiationFailed => Error::UnsupportedProtocol,
+				};
+				self.pending_events.push_back(Err(error));
+			}
+			ConnectionEvent::AddressChange(_) => {}
+		}
+	}
+
+	#[tracing::instrument(level = "debug", name = "IdentifyHandler::poll", skip(cx, self))]
+	fn poll(&mut self, cx: &mut std::task::Context<'_>) -> Poll<ProtocolHandlerEvent<Self::ToProtocol>> {
+		loop {
+			if let Some(result) = self.pending_events.pop_front() {
+				return Poll::Ready(ProtocolHandlerEvent::NotifyProtocol(result));
+			}
+
+			match self.inbound.poll_next_unpin(cx) {
+				Poll::Ready(Some(Ok(info))) => {
+					return Poll::Ready(ProtocolHandlerEvent::NotifyProtocol(Ok(Received {
+						supported_protocols: info.supported_protocols,
+						observed_addr: info.observed_addr,
+					})));
+				}
+				Poll::Ready(Some(Err(error))) => {
+					self.pending_events.push_back(Err(Error::Io(error)));
+					continue;
+				}
+				Poll::Ready(None) | Poll::Pending => {}
+			}
+
+			match self.outbound.poll_next_unpin(cx) {
+				Poll::Ready(Some(Err(error))) => {
+					self.pending_events.push_back(Err(Error::Io(error)));
+					continue;
+				}
+				Poll::Ready(Some(Ok(()))) => continue,
+				Poll::Ready(None) | Poll::Pending => {}
+			}
+
+			if !self.closing && self.pending_pushes.len() > self.outbound_requested {
+				self.outbound_requested += 1;
+				return Poll::Ready(ProtocolHandlerEvent::OutboundSubstreamRequest);
+			}
+
+			return Poll::Pending;
+		}
+	}
+
+	fn poll_close(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Option<Self::ToProtocol>> {
+		self.closing = true;
+
+		match self.poll(cx) {
+			Poll::Ready(ProtocolHandlerEvent::NotifyProtocol(event)) => Poll::Ready(Some(event)),
+			Poll::Ready(ProtocolHandlerEvent::OutboundSubstreamRequest) => {
+				unreachable!("poll() must not request a new outbound substream while closing")
+			}
+			Poll::Pending => {
+				let idle = self.outbound.is_empty() && self.inbound.is_empty();
+				if idle { Poll::Ready(None) } else { Poll::Pending }
+			}
+		}
+	}
+}
+
+fn push(stream: BoxedStream, info: Info) -> BoxFuture<'static, std::io::Result<()>> {
+	async move {
+		protocol::send_identify(stream, &info).await?;
+		Ok(())
+	}
+	.boxed()
+}
@@ -0,0 +1,135 @@
+use std::{collections::VecDeque, task::Poll};
+
+use libp2p_identity::PublicKey;
+use multiaddr::{Multiaddr, PeerId};
+use rs_mojave_transport_node::{Action, ConnectionId, FromNode, NotifyTarget, PeerProtocol, StreamProtocol};
+
+mod config;
+mod error;
+mod handler;
+mod protocol;
+
+use crate::handler::{Command, Handler};
+
+pub use config::Config;
+pub use error::Error;
+pub use handler::Received;
+pub use protocol::{Info, PROTOCOL_NAME};
+
+/// An `identify` [`PeerProtocol`], sibling to `ping`: on each new connection it pushes a snapshot
+/// of this node's public key, protocol version, supported [`StreamProtocol`]s, and listen
+/// addresses to the peer over `rs-mojave/identify@0.0.1`, and reports back the supported protocols
+/// and observed address from each push the peer sends us (see [`Received`]).
+/// [`Protocol::update_listen_addrs`] and
+/// [`Protocol::update_supported_protocols`] queue a fresh push to every connection so peers learn
+/// of address or protocol-set changes without waiting for a new connection.
+pub struct Protocol {
+	config: Config,
+	public_key: PublicKey,
+
+	/// Every connection [`Protocol::on_new_connection`] has seen, so
+	/// [`Protocol::update_listen_addrs`]/[`Protocol::update_supported_protocols`] can target a push
+	/// at each of them -- [`Action::Notify`] always addresses one connection at a time, never a
+	/// broadcast. A connection that's since closed just makes its queued push a harmless no-op
+	/// (see [`rs_mojave_transport_node`]'s `Manager::send_to_target`); entries aren't removed here
+	/// since `PeerProtocol` has no connection-closed hook to remove them on.
+	connections: Vec<(PeerId, ConnectionId)>,
+
+	/// Commands queued by [`Protocol::update_listen_addrs`]/[`Protocol::update_supported_protocols`],
+	/// one per tracked connection, waiting to be handed to that connection's [`Handler`] the next
+	/// time it's polled.
+	pending_commands: VecDeque<(NotifyTarget, Command)>,
+
+	events: VecDeque<Event>,
+}
+
+#[derive(Debug)]
+pub struct Event {
+	pub peer: PeerId,
+	pub connection_id: ConnectionId,
+	pub result: Result<Received, Error>,
+}
+
+impl Protocol {
+	pub fn new(config: Config, public_key: PublicKey) -> Self {
+		Self {
+			config,
+			public_key,
+			connections: Vec::new(),
+			pending_commands: VecDeque::new(),
+			events: VecDeque::new(),
+		}
+	}
+
+	fn info(&self, observed_addr: Multiaddr) -> Info {
+		Info {
+			public_key: self.public_key.encode_protobuf(),
+			protocol_version: self.config.protocol_version().to_owned(),
+			supported_protocols: self.config.supported_protocols().to_vec(),
+			listen_addrs: self.config.listen_addrs().to_vec(),
+			observed_addr,
+		}
+	}
+
+	/// Updates the supported-protocol set advertised to peers and queues a push of the new
+	/// [`Info`] to every open connection. `observed_addr` was already reported once per
+	/// connection when it was established, so these out-of-band pushes leave it empty.
+	pub fn update_supported_protocols(&mut self, protocols: Vec<StreamProtocol>) {
+		self.config.set_supported_protocols(protocols);
+		self.queue_push_to_all();
+	}
+
+	/// Updates the listen-address set advertised to peers and queues a push of the new [`Info`]
+	/// to every open connection. See [`Protocol::update_supported_protocols`] for why
+	/// `observed_addr` is left empty here.
+	pub fn update_listen_addrs(&mut self, addrs: Vec<Multiaddr>) {
+		self.config.set_listen_addrs(addrs);
+		self.queue_push_to_all();
+	}
+
+	/// Queues one [`Command::Push`] per tracked connection, each targeted individually via
+	/// [`NotifyTarget::Connection`].
+	fn queue_push_to_all(&mut self) {
+		let info = self.info(Multiaddr::empty());
+		for &(peer_id, connection_id) in &self.connections {
+			self.pending_commands
+				.push_back((NotifyTarget::Connection(peer_id, connection_id), Command::Push(info.clone())));
+		}
+	}
+}
+
+impl PeerProtocol for Protocol {
+	type ToNode = Event;
+
+	type Handler = Handler;
+
+	#[tracing::instrument(level = "debug", name = "Identify::OnNewConnection", skip(self))]
+	fn on_new_connection(
+		&mut self,
+		connection_id: ConnectionId,
+		peer_id: PeerId,
+		remote_addr: &Multiaddr,
+		_local_addr: Option<&Multiaddr>,
+	) -> Result<Self::Handler, rs_mojave_transport_node::ConnectionError> {
+		self.connections.push((peer_id, connection_id));
+		let initial = self.info(remote_addr.clone());
+		Ok(Handler::new(PROTOCOL_NAME.clone(), initial))
+	}
+
+	fn on_node_event(&mut self, _: FromNode) {}
+
+	fn poll(
+		&mut self,
+		_: &mut std::task::Context<'_>,
+	) -> Poll<Action<Self::ToNode, rs_mojave_transport_node::THandlerFromEvent<Self>>> {
+		if let Some(event) = self.events.pop_front() {
+			return Poll::Ready(Action::Event(event));
+		}
+
+		if let Some((target, command)) = self.pending_commands.pop_front() {
+			return Poll::Ready(Action::Notify { target, event: command });
+		}
+
+		Poll::Pending
+	}
+}
@@ -0,0 +1,53 @@
+use std::{io, sync::LazyLock};
+
+use asynchronous_codec::{Framed, LengthCodec};
+use futures::prelude::*;
+use multiaddr::Multiaddr;
+use rs_mojave_transport_node::StreamProtocol;
+use semver::Version;
+
+pub const PROTOCOL_VERSION: Version = Version::new(0, 0, 1);
+pub static PROTOCOL_NAME: LazyLock<StreamProtocol> =
+	LazyLock::new(|| StreamProtocol::new("rs-mojave", "identify", PROTOCOL_VERSION));
+
+/// The message pushed over an identify substream: a snapshot of the sender's public key,
+/// supported protocols, and listen addresses, plus the address the sender observed the remote
+/// connecting from.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Info {
+	/// The sender's public key, protobuf-encoded via [`libp2p_identity::PublicKey::encode_protobuf`].
+	pub public_key: Vec<u8>,
+	pub protocol_version: String,
+	pub supported_protocols: Vec<StreamProtocol>,
+	pub listen_addrs: Vec<Multiaddr>,
+	pub observed_addr: Multiaddr,
+}
+
+/// Pushes `info` over a freshly opened substream, then closes it.
+pub(crate) async fn send_identify<S>(stream: S, info: &Info) -> io::Result<S>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let mut io = Framed::new(stream, LengthCodec);
+
+	let payload = serde_json::to_vec(info).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+	io.send(payload.into()).await?;
+	io.close().await?;
+
+	Ok(io.into_inner())
+}
+
+/// Reads the single [`Info`] message a peer pushes over a freshly opened substream.
+pub(crate) async fn recv_identify<S>(stream: S) -> io::Result<Info>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let mut io = Framed::new(stream, LengthCodec);
+
+	let msg = io
+		.next()
+		.await
+		.ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "identify substream closed before a message arrived"))??;
+
+	serde_json::from_slice(msg.as_ref()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
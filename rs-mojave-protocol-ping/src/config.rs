@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{num::NonZeroU32, time::Duration};
 
 /// The configuration for outbound pings.
 #[derive(Debug, Clone)]
@@ -7,6 +7,8 @@ pub struct Config {
 	timeout: Duration,
 	/// The duration between outbound pings.
 	interval: Duration,
+	/// The number of consecutive ping failures after which the connection is considered dead.
+	max_failures: NonZeroU32,
 }
 
 impl Config {
@@ -14,15 +16,18 @@ impl Config {
 	///
 	///   * [`Config::with_interval`] 15s
 	///   * [`Config::with_timeout`] 20s
+	///   * [`Config::with_max_failures`] 1
 	///
 	/// These settings have the following effect:
 	///
 	///   * A ping is sent every 15 seconds on a healthy connection.
 	///   * Every ping sent must yield a response within 20 seconds in order to be successful.
+	///   * A single failed ping is reported as [`crate::Error::Unresponsive`].
 	pub fn new() -> Self {
 		Self {
 			timeout: Duration::from_secs(20),
 			interval: Duration::from_secs(15),
+			max_failures: NonZeroU32::new(1).expect("1 is non-zero"),
 		}
 	}
 
@@ -37,6 +42,13 @@ impl Config {
 		self.interval = d;
 		self
 	}
+
+	/// Sets the number of consecutive ping failures tolerated before the connection is reported
+	/// as [`crate::Error::Unresponsive`].
+	pub fn with_max_failures(mut self, n: NonZeroU32) -> Self {
+		self.max_failures = n;
+		self
+	}
 }
 
 impl Default for Config {
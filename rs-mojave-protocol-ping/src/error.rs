@@ -1,9 +1,11 @@
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-	#[error("timeout {0}")]
-	Timeout(u64),
+	#[error("timeout after {0:?}")]
+	Timeout(std::time::Duration),
 	#[error("unsupported protocol")]
 	UnsupportedProtocol,
+	#[error("peer is unresponsive: {failures} consecutive ping failures")]
+	Unresponsive { failures: u32 },
 	#[error("Other error: {0}")]
 	Other(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
 	#[error(transparent)]
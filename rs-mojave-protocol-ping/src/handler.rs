@@ -6,7 +6,7 @@ use futures::{
 };
 use futures_timer::Delay;
 use rs_mojave_transport_node::{
-	AsyncReadWrite, ConnectionId, FromNode, PeerProtocol, ProtocolHandler, ProtocolHandlerEvent, StreamProtocol,
+	AsyncReadWrite, ConnectionId, FromNode, PeerProtocol, ProtocolHandler, ProtocolHandlerEvent, ProtocolInfo,
 };
 
 use crate::{Config, Error, PROTOCOL_NAME, protocol};
@@ -26,6 +26,9 @@ enum OutboundState {
 	Idle(Box<dyn AsyncReadWrite + Send + Unpin>),
 	/// A ping is being sent and the response awaited.
 	Ping(PingFuture),
+	/// The handler is shutting down, or the peer was reported unresponsive: no further pings will
+	/// be sent and `interval` is not re-armed.
+	Closed,
 }
 
 /// A wrapper around [`protocol::send_ping`] that enforces a time out.
@@ -39,7 +42,7 @@ async fn send_ping(
 	match future::select(ping, Delay::new(timeout)).await {
 		Either::Left((Ok((stream, rtt)), _)) => Ok((stream, rtt)),
 		Either::Left((Err(e), _)) => Err(Error::Io(e)),
-		Either::Right(((), _)) => Err(Error::Timeout(10)),
+		Either::Right(((), _)) => Err(Error::Timeout(timeout)),
 	}
 }
 
@@ -51,6 +54,10 @@ pub struct Handler {
 	outbound: Option<OutboundState>,
 	pong: Option<PongFuture>,
 	pending_errors: VecDeque<Error>,
+	/// Set once `poll_close` has been called; stops new pings and rejects new inbound substreams.
+	closing: bool,
+	/// The number of consecutive ping failures since the last successful pong.
+	failures: u32,
 }
 
 impl Handler {
@@ -62,6 +69,8 @@ impl Handler {
 			pending_errors: Default::default(),
 			outbound: None,
 			pong: None,
+			closing: false,
+			failures: 0,
 		}
 	}
 }
@@ -69,20 +78,27 @@ impl Handler {
 impl ProtocolHandler for Handler {
 	type FromProtocol = Infallible;
 	type ToProtocol = Result<Duration, Error>;
-	type ProtocolInfoIter = iter::Once<StreamProtocol>;
+	type ProtocolInfoIter = iter::Once<ProtocolInfo>;
 
 	fn protocol_info(&self) -> Self::ProtocolInfoIter {
-		iter::once(PROTOCOL_NAME.clone())
+		iter::once(ProtocolInfo::Exact(PROTOCOL_NAME.clone()))
 	}
 
 	fn on_protocol_event(&mut self, _: Self::FromProtocol) {}
 
 	fn on_connection_event(&mut self, event: rs_mojave_transport_node::ConnectionEvent) {
 		match event {
-			rs_mojave_transport_node::ConnectionEvent::NewInboundStream(substream_box) => {
+			rs_mojave_transport_node::ConnectionEvent::NewInboundStream(_protocol, substream_box) => {
+				if self.closing {
+					// Draining: don't accept new work on a handler that's shutting down.
+					return;
+				}
 				self.pong = Some(protocol::recv_ping(substream_box).boxed());
 			}
-			rs_mojave_transport_node::ConnectionEvent::NewOutboundStream(substream_box) => {
+			rs_mojave_transport_node::ConnectionEvent::NewOutboundStream(_protocol, substream_box) => {
+				if self.closing {
+					return;
+				}
 				self.outbound = Some(OutboundState::Ping(
 					send_ping(substream_box, self.config.timeout).boxed(),
 				));
@@ -122,7 +138,13 @@ impl ProtocolHandler for Handler {
 		if let Some(fut) = self.pong.as_mut() {
 			match fut.poll_unpin(cx) {
 				Poll::Pending => {}
-				Poll::Ready(Ok(stream)) => self.pong = Some(protocol::recv_ping(stream).boxed()),
+				Poll::Ready(Ok(stream)) => {
+					self.pong = if self.closing {
+						None
+					} else {
+						Some(protocol::recv_ping(stream).boxed())
+					};
+				}
 				Poll::Ready(Err(err)) => {
 					tracing::error!("Handler::poll: {:?}", err);
 					self.pong = None;
@@ -143,26 +165,50 @@ impl ProtocolHandler for Handler {
 						break;
 					}
 
-					OutboundState::Idle(stream) => match self.interval.poll_unpin(cx) {
-						Poll::Ready(_) => {
-							self.outbound = Some(OutboundState::Ping(send_ping(stream, self.config.timeout).boxed()));
-						}
-						Poll::Pending => {
-							self.outbound = Some(OutboundState::Idle(stream));
+					OutboundState::Idle(stream) => {
+						if self.closing {
+							self.outbound = Some(OutboundState::Closed);
 							break;
 						}
-					},
+
+						match self.interval.poll_unpin(cx) {
+							Poll::Ready(_) => {
+								self.outbound = Some(OutboundState::Ping(send_ping(stream, self.config.timeout).boxed()));
+							}
+							Poll::Pending => {
+								self.outbound = Some(OutboundState::Idle(stream));
+								break;
+							}
+						}
+					}
 
 					OutboundState::Ping(mut ping) => match ping.poll_unpin(cx) {
 						Poll::Ready(e) => match e {
 							Ok((stream, rtt)) => {
 								tracing::info!(?rtt, "PingHandler::ping succeeded");
-								self.interval.reset(self.config.interval);
-								self.outbound = Some(OutboundState::Idle(stream));
+								self.failures = 0;
+								if self.closing {
+									self.outbound = Some(OutboundState::Closed);
+								} else {
+									self.interval.reset(self.config.interval);
+									self.outbound = Some(OutboundState::Idle(stream));
+								}
 								return Poll::Ready(ProtocolHandlerEvent::NotifyProtocol(Ok(rtt)));
 							}
 							Err(e) => {
-								self.interval.reset(self.config.interval);
+								self.failures += 1;
+								if self.failures >= self.config.max_failures.get() {
+									tracing::error!(failures = self.failures, "PingHandler: peer is unresponsive");
+									self.outbound = Some(OutboundState::Closed);
+									return Poll::Ready(ProtocolHandlerEvent::NotifyProtocol(Err(Error::Unresponsive {
+										failures: self.failures,
+									})));
+								}
+								if self.closing {
+									self.outbound = Some(OutboundState::Closed);
+								} else {
+									self.interval.reset(self.config.interval);
+								}
 								self.pending_errors.push_front(e);
 							}
 						},
@@ -171,9 +217,18 @@ impl ProtocolHandler for Handler {
 							break;
 						}
 					},
+
+					OutboundState::Closed => {
+						self.outbound = Some(OutboundState::Closed);
+						break;
+					}
 				},
 
 				None => match self.interval.poll_unpin(cx) {
+					Poll::Ready(_) if self.closing => {
+						self.outbound = Some(OutboundState::Closed);
+						break;
+					}
 					Poll::Ready(_) => {
 						self.outbound = Some(OutboundState::OpenStream);
 						return Poll::Ready(ProtocolHandlerEvent::OutboundSubstreamRequest);
@@ -185,4 +240,29 @@ impl ProtocolHandler for Handler {
 
 		Poll::Pending
 	}
+
+	fn poll_close(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Option<Self::ToProtocol>> {
+		self.closing = true;
+
+		if let Some(error) = self.pending_errors.pop_front() {
+			return Poll::Ready(Some(Err(error)));
+		}
+
+		// Drive the in-flight ping (if any) to completion so its result isn't lost, but don't
+		// re-arm `interval` or request a new outbound substream once it settles.
+		match self.poll(cx) {
+			Poll::Ready(ProtocolHandlerEvent::NotifyProtocol(event)) => Poll::Ready(Some(event)),
+			Poll::Ready(ProtocolHandlerEvent::OutboundSubstreamRequest) => {
+				unreachable!("poll() must not request a new outbound substream while closing")
+			}
+			Poll::Pending => {
+				let idle = matches!(self.outbound, None | Some(OutboundState::Closed));
+				if idle && self.pong.is_none() {
+					Poll::Ready(None)
+				} else {
+					Poll::Pending
+				}
+			}
+		}
+	}
 }
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use rs_mojave_transport_node::StreamProtocol;
+
+/// The configuration for a [`crate::RequestResponse`] instance.
+#[derive(Debug, Clone)]
+pub struct Config {
+	/// The protocol name this instance negotiates. Callers pick their own, since a single
+	/// request-response wire format can back many different application protocols.
+	protocol_name: StreamProtocol,
+	/// How long we wait for the response after sending a request before giving up.
+	request_timeout: Duration,
+}
+
+impl Config {
+	/// Creates a new [`Config`] for the given protocol name with the following default settings:
+	///
+	///   * [`Config::with_request_timeout`] 10s
+	pub fn new(protocol_name: StreamProtocol) -> Self {
+		Self {
+			protocol_name,
+			request_timeout: Duration::from_secs(10),
+		}
+	}
+
+	/// Sets how long we wait for the response before the request is reported as
+	/// [`crate::Error::Timeout`].
+	pub fn with_request_timeout(mut self, d: Duration) -> Self {
+		self.request_timeout = d;
+		self
+	}
+
+	pub fn protocol_name(&self) -> &StreamProtocol {
+		&self.protocol_name
+	}
+
+	pub fn request_timeout(&self) -> Duration {
+		self.request_timeout
+	}
+}
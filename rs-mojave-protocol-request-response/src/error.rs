@@ -0,0 +1,11 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("timeout after {0:?}")]
+	Timeout(std::time::Duration),
+	#[error("unsupported protocol")]
+	UnsupportedProtocol,
+	#[error("codec error: {0}")]
+	Codec(std::io::Error),
+	#[error(transparent)]
+	Io(#[from] std::io::Error),
+}
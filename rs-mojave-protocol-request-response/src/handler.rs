@@ -0,0 +1,248 @@
+use std::{collections::VecDeque, fmt, io, iter, sync::Arc, task::Poll, time::Duration};
+
+use futures::{
+	StreamExt,
+	channel::oneshot,
+	future::{BoxFuture, Either, FutureExt},
+	stream::FuturesUnordered,
+};
+use futures_timer::Delay;
+use rs_mojave_transport_node::{AsyncReadWrite, ConnectionEvent, ProtocolHandler, ProtocolHandlerEvent, ProtocolInfo, StreamProtocol};
+
+use crate::{Codec, Config, Error, protocol};
+
+type BoxedStream = Box<dyn AsyncReadWrite + Send + Unpin>;
+
+/// A request queued by [`crate::RequestResponse::request`], waiting for an outbound substream to
+/// carry it.
+pub(crate) struct OutboundRequest<C: Codec> {
+	pub(crate) request: C::Request,
+	pub(crate) response: oneshot::Sender<Result<C::Response, Error>>,
+}
+
+/// A command delivered to a single connection's [`Handler`] by the owning
+/// [`crate::RequestResponse`].
+pub enum Command<C: Codec> {
+	SendRequest(OutboundRequest<C>),
+}
+
+impl<C: Codec> fmt::Debug for Command<C> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Command::SendRequest(_) => f.debug_tuple("SendRequest").finish_non_exhaustive(),
+		}
+	}
+}
+
+/// Notifications a [`Handler`] reports back up to [`crate::RequestResponse`].
+pub enum Event<C: Codec> {
+	/// A remote peer opened a substream and sent a request. The application replies by sending
+	/// its response into `response`; dropping `response` without sending closes the substream
+	/// without a reply.
+	InboundRequest {
+		request: C::Request,
+		response: oneshot::Sender<C::Response>,
+	},
+}
+
+impl<C: Codec> fmt::Debug for Event<C> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Event::InboundRequest { .. } => f.debug_struct("InboundRequest").finish_non_exhaustive(),
+		}
+	}
+}
+
+/// Sends the request on a freshly opened outbound substream, reads the single response frame, and
+/// resolves `outbound.response` with the result. Success, codec failures, and connection loss
+/// mid-exchange all surface to the caller this way -- there's no separate failure event.
+async fn run_outbound<C: Codec>(codec: Arc<C>, mut stream: BoxedStream, outbound: OutboundRequest<C>, timeout: Duration) {
+	let OutboundRequest { request, response } = outbound;
+
+	let exchange = async {
+		let payload = codec.encode_request(&request).map_err(Error::Codec)?;
+		protocol::write_frame(&mut stream, &payload).await?;
+		let bytes = protocol::read_frame(&mut stream).await?;
+		codec.decode_response(&bytes).map_err(Error::Codec)
+	};
+	futures::pin_mut!(exchange);
+
+	let result = match futures::future::select(exchange, Delay::new(timeout)).await {
+		Either::Left((result, _)) => result,
+		Either::Right(((), _)) => Err(Error::Timeout(timeout)),
+	};
+
+	// The caller dropped its receiver; nothing left to do.
+	let _ = response.send(result);
+}
+
+/// Waits for the application to produce its response, then writes it as a single length-prefixed
+/// frame. Resolves without writing anything if the application drops `response` without sending.
+async fn write_response<C: Codec>(codec: Arc<C>, mut stream: BoxedStream, response: oneshot::Receiver<C::Response>) {
+	let Ok(response) = response.await else { return };
+
+	let payload = match codec.encode_response(&response) {
+		Ok(bytes) => bytes,
+		Err(_) => return,
+	};
+	let _ = protocol::write_frame(&mut stream, &payload).await;
+}
+
+pub struct Handler<C: Codec> {
+	codec: Arc<C>,
+	protocol_name: StreamProtocol,
+	request_timeout: Duration,
+
+	/// Requests that have not yet been handed an outbound substream.
+	pending_requests: VecDeque<OutboundRequest<C>>,
+	/// Number of [`ProtocolHandlerEvent::OutboundSubstreamRequest`]s we've emitted that haven't
+	/// been fulfilled by a matching [`ConnectionEvent::NewOutboundStream`] (or failed negotiation)
+	/// yet.
+	outbound_streams_requested: usize,
+	/// Outbound substreams currently exchanging their request/response.
+	outbound: FuturesUnordered<BoxFuture<'static, ()>>,
+
+	/// Inbound substreams currently reading and decoding their request frame.
+	inbound_reading: FuturesUnordered<BoxFuture<'static, io::Result<(BoxedStream, Vec<u8>)>>>,
+	/// Inbound substreams currently waiting on the application's response to write it out.
+	inbound_writing: FuturesUnordered<BoxFuture<'static, ()>>,
+
+	pending_events: VecDeque<Event<C>>,
+
+	/// Set once `poll_close` has been called; stops accepting new work.
+	closing: bool,
+}
+
+impl<C: Codec> Handler<C> {
+	pub fn new(config: Config, codec: Arc<C>) -> Self {
+		Self {
+			codec,
+			protocol_name: config.protocol_name().clone(),
+			request_timeout: config.request_timeout(),
+			pending_requests: VecDeque::new(),
+			outbound_streams_requested: 0,
+			outbound: FuturesUnordered::new(),
+			inbound_reading: FuturesUnordered::new(),
+			inbound_writing: FuturesUnordered::new(),
+			pending_events: VecDeque::new(),
+			closing: false,
+		}
+	}
+}
+
+impl<C: Codec> ProtocolHandler for Handler<C> {
+	type FromProtocol = Command<C>;
+	type ToProtocol = Event<C>;
+	type ProtocolInfoIter = iter::Once<ProtocolInfo>;
+
+	fn protocol_info(&self) -> Self::ProtocolInfoIter {
+		iter::once(ProtocolInfo::Exact(self.protocol_name.clone()))
+	}
+
+	fn on_protocol_event(&mut self, event: Self::FromProtocol) {
+		match event {
+			Command::SendRequest(outbound) => self.pending_requests.push_back(outbound),
+		}
+	}
+
+	fn on_connection_event(&mut self, event: ConnectionEvent) {
+		match event {
+			ConnectionEvent::NewOutboundStream(_protocol, stream) => {
+				self.outbound_streams_requested = self.outbound_streams_requested.saturating_sub(1);
+				if let Some(outbound) = self.pending_requests.pop_front() {
+					self.outbound
+						.push(run_outbound(self.codec.clone(), stream, outbound, self.request_timeout).boxed());
+				}
+			}
+			ConnectionEvent::NewInboundStream(_protocol, stream) => {
+				if self.closing {
+					// Draining: don't accept new work on a handler that's shutting down.
+					return;
+				}
+				self.inbound_reading.push(protocol::read_request(stream).boxed());
+			}
+			ConnectionEvent::FailNegotiation(err) => {
+				self.outbound_streams_requested = self.outbound_streams_requested.saturating_sub(1);
+				let error = match err {
+					rs_mojave_transport_node::negotiator::NegotiatorStreamError::Timeout => {
+						Error::Io(io::Error::new(io::ErrorKind::TimedOut, "request-response negotiation timed out"))
+					}
+					rs_mojave_transport_node::negotiator::NegotiatorStreamError::IoError(error) => Error::Io(error),
+					rs_mojave_transport_node::negotiator::NegotiatorStreamError::NegotiationFailed => Error::UnsupportedProtocol,
+				};
+				if let Some(outbound) = self.pending_requests.pop_front() {
+					let _ = outbound.response.send(Err(error));
+				}
+			}
+			ConnectionEvent::AddressChange(_) => {}
+		}
+	}
+
+	#[tracing::instrument(level = "debug", name = "RequestResponseHandler::poll", skip(cx, self))]
+	fn poll(&mut self, cx: &mut std::task::Context<'_>) -> Poll<ProtocolHandlerEvent<Self::ToProtocol>> {
+		loop {
+			if let Some(event) = self.pending_events.pop_front() {
+				return Poll::Ready(ProtocolHandlerEvent::NotifyProtocol(event));
+			}
+
+			match self.outbound.poll_next_unpin(cx) {
+				Poll::Ready(Some(())) => continue,
+				Poll::Ready(None) | Poll::Pending => {}
+			}
+
+			match self.inbound_reading.poll_next_unpin(cx) {
+				Poll::Ready(Some(Ok((stream, bytes)))) => {
+					match self.codec.decode_request(&bytes) {
+						Ok(request) => {
+							let (response_tx, response_rx) = oneshot::channel();
+							self.inbound_writing
+								.push(write_response(self.codec.clone(), stream, response_rx).boxed());
+							self.pending_events.push_back(Event::InboundRequest {
+								request,
+								response: response_tx,
+							});
+						}
+						Err(error) => tracing::debug!(?error, "Handler::poll: failed to decode inbound request"),
+					}
+					continue;
+				}
+				Poll::Ready(Some(Err(error))) => {
+					tracing::debug!(?error, "Handler::poll: failed to read inbound request");
+					continue;
+				}
+				Poll::Ready(None) | Poll::Pending => {}
+			}
+
+			match self.inbound_writing.poll_next_unpin(cx) {
+				Poll::Ready(Some(())) => continue,
+				Poll::Ready(None) | Poll::Pending => {}
+			}
+
+			if !self.closing && self.pending_requests.len() > self.outbound_streams_requested {
+				self.outbound_streams_requested += 1;
+				return Poll::Ready(ProtocolHandlerEvent::OutboundSubstreamRequest);
+			}
+
+			return Poll::Pending;
+		}
+	}
+
+	fn poll_close(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Option<Self::ToProtocol>> {
+		self.closing = true;
+
+		match self.poll(cx) {
+			Poll::Ready(ProtocolHandlerEvent::NotifyProtocol(event)) => Poll::Ready(Some(event)),
+			Poll::Ready(ProtocolHandlerEvent::OutboundSubstreamRequest) => {
+				unreachable!("poll() must not request a new outbound substream while closing")
+			}
+			Poll::Pending => {
+				let idle = self.outbound.is_empty() && self.inbound_reading.is_empty() && self.inbound_writing.is_empty();
+				if idle {
+					Poll::Ready(None)
+				} else {
+					Poll::Pending
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,99 @@
+use std::{collections::VecDeque, sync::Arc, task::Poll};
+
+use futures::channel::oneshot;
+use multiaddr::PeerId;
+use rs_mojave_transport_node::{Action, ConnectionId, FromNode, NotifyTarget, PeerProtocol};
+
+mod codec;
+mod config;
+mod error;
+mod handler;
+mod protocol;
+
+use crate::handler::{Command, Handler, OutboundRequest};
+
+pub use codec::Codec;
+pub use config::Config;
+pub use error::Error;
+pub use handler::Event;
+
+/// A request/response [`PeerProtocol`]: a single request yields exactly one response, unlike
+/// [`rs_mojave_protocol_streaming_response`]'s many ordered frames.
+pub struct RequestResponse<C: Codec> {
+	config: Config,
+	codec: Arc<C>,
+
+	/// Requests queued via [`RequestResponse::request`], waiting to be handed to the target
+	/// peer's [`Handler`] the next time it's polled. Keyed by the target peer -- see the note on
+	/// delivery on [`RequestResponse::request`].
+	pending_commands: VecDeque<(PeerId, Command<C>)>,
+
+	events: VecDeque<Event<C>>,
+}
+
+impl<C: Codec> RequestResponse<C> {
+	pub fn new(config: Config, codec: C) -> Self {
+		Self {
+			config,
+			codec: Arc::new(codec),
+			pending_commands: VecDeque::new(),
+			events: VecDeque::new(),
+		}
+	}
+
+	/// Sends `req` to `peer` and returns a channel that resolves with its response. Resolves with
+	/// an [`Error`] if the exchange fails: [`Error::Timeout`] if the peer doesn't respond in time,
+	/// or an I/O or codec error if the substream fails or the response can't be decoded.
+	///
+	/// The request goes to one established connection to `peer`, picked arbitrarily. If `peer` has
+	/// no established connection when the request is dispatched, the request is dropped and the
+	/// channel resolves with [`oneshot::Canceled`].
+	pub fn request(&mut self, peer: PeerId, req: C::Request) -> oneshot::Receiver<Result<C::Response, Error>> {
+		let (response_tx, response_rx) = oneshot::channel();
+		self.pending_commands.push_back((
+			peer,
+			Command::SendRequest(OutboundRequest {
+				request: req,
+				response: response_tx,
+			}),
+		));
+		response_rx
+	}
+}
+
+impl<C: Codec> PeerProtocol for RequestResponse<C> {
+	type ToNode = Event<C>;
+
+	type Handler = Handler<C>;
+
+	#[tracing::instrument(level = "debug", name = "RequestResponse::OnNewConnection", skip(self))]
+	fn on_new_connection(
+		&mut self,
+		_connection_id: ConnectionId,
+		_peer_id: PeerId,
+		_remote_addr: &multiaddr::Multiaddr,
+		_local_addr: Option<&multiaddr::Multiaddr>,
+	) -> Result<Self::Handler, rs_mojave_transport_node::ConnectionError> {
+		Ok(Handler::new(self.config.clone(), self.codec.clone()))
+	}
+
+	fn on_node_event(&mut self, _: FromNode) {}
+
+	fn poll(
+		&mut self,
+		_: &mut std::task::Context<'_>,
+	) -> Poll<Action<Self::ToNode, rs_mojave_transport_node::THandlerFromEvent<Self>>> {
+		if let Some(event) = self.events.pop_front() {
+			return Poll::Ready(Action::Event(event));
+		}
+
+		if let Some((peer, command)) = self.pending_commands.pop_front() {
+			return Poll::Ready(Action::Notify {
+				target: NotifyTarget::Peer(peer),
+				event: command,
+			});
+		}
+
+		Poll::Pending
+	}
+}
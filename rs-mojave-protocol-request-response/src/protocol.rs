@@ -0,0 +1,47 @@
+use std::io;
+
+use futures::prelude::*;
+
+/// Generous upper bound on a single frame, so a malformed or malicious peer can't make us
+/// allocate an unbounded buffer while reading the length prefix.
+pub const MAX_FRAME_LEN: u32 = 16 * 1024 * 1024;
+
+/// Writes a single length-prefixed frame.
+pub(crate) async fn write_frame<S>(stream: &mut S, payload: &[u8]) -> io::Result<()>
+where
+	S: AsyncWrite + Unpin,
+{
+	let len = u32::try_from(payload.len())
+		.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame exceeds u32::MAX bytes"))?;
+	stream.write_all(&len.to_be_bytes()).await?;
+	stream.write_all(payload).await?;
+	stream.flush().await
+}
+
+/// Reads a single length-prefixed frame.
+pub(crate) async fn read_frame<S>(stream: &mut S) -> io::Result<Vec<u8>>
+where
+	S: AsyncRead + Unpin,
+{
+	let mut len_buf = [0u8; 4];
+	stream.read_exact(&mut len_buf).await?;
+	let len = u32::from_be_bytes(len_buf);
+	if len > MAX_FRAME_LEN {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("frame of {len} bytes exceeds the {MAX_FRAME_LEN} byte limit"),
+		));
+	}
+	let mut payload = vec![0u8; len as usize];
+	stream.read_exact(&mut payload).await?;
+	Ok(payload)
+}
+
+/// Reads the single request frame a freshly opened inbound substream starts with.
+pub(crate) async fn read_request<S>(mut stream: S) -> io::Result<(S, Vec<u8>)>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let bytes = read_frame(&mut stream).await?;
+	Ok((stream, bytes))
+}
@@ -0,0 +1,17 @@
+use std::io;
+
+/// (De)serializes the request/response payloads carried by a [`crate::StreamingResponse`]
+/// substream.
+///
+/// Framing -- the length prefix and end-of-stream marker every response frame is wrapped in --
+/// is handled by the protocol itself, so implementations only need to convert to and from raw
+/// bytes.
+pub trait Codec: Send + Sync + 'static {
+	type Request: Send + 'static;
+	type Response: Send + 'static;
+
+	fn encode_request(&self, request: &Self::Request) -> io::Result<Vec<u8>>;
+	fn decode_request(&self, bytes: &[u8]) -> io::Result<Self::Request>;
+	fn encode_response(&self, response: &Self::Response) -> io::Result<Vec<u8>>;
+	fn decode_response(&self, bytes: &[u8]) -> io::Result<Self::Response>;
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use rs_mojave_transport_node::StreamProtocol;
+
+/// The configuration for a [`crate::StreamingResponse`] instance.
+#[derive(Debug, Clone)]
+pub struct Config {
+	/// The protocol name this instance negotiates. Callers pick their own, since a single
+	/// streaming-response wire format can back many different application protocols.
+	protocol_name: StreamProtocol,
+	/// Stored and returned by [`Config::request_timeout`]; the streaming-response handler does not
+	/// apply it, so requests are not timed out.
+	request_timeout: Duration,
+}
+
+impl Config {
+	/// Creates a new [`Config`] for the given protocol name with the following default settings:
+	///
+	///   * [`Config::with_request_timeout`] 10s
+	pub fn new(protocol_name: StreamProtocol) -> Self {
+		Self {
+			protocol_name,
+			request_timeout: Duration::from_secs(10),
+		}
+	}
+
+	/// Sets the value returned by [`Config::request_timeout`]. The streaming-response handler does
+	/// not read it: no request is reported as [`crate::Error::Timeout`].
+	pub fn with_request_timeout(mut self, d: Duration) -> Self {
+		self.request_timeout = d;
+		self
+	}
+
+	pub fn protocol_name(&self) -> &StreamProtocol {
+		&self.protocol_name
+	}
+
+	pub fn request_timeout(&self) -> Duration {
+		self.request_timeout
+	}
+}
@@ -0,0 +1,13 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("timeout after {0:?}")]
+	Timeout(std::time::Duration),
+	#[error("unsupported protocol")]
+	UnsupportedProtocol,
+	#[error("frame of {actual} bytes exceeds the {max} byte limit")]
+	FrameTooLarge { max: u32, actual: u32 },
+	#[error("codec error: {0}")]
+	Codec(std::io::Error),
+	#[error(transparent)]
+	Io(#[from] std::io::Error),
+}
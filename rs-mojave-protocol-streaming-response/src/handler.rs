@@ -0,0 +1,264 @@
+use std::{collections::VecDeque, fmt, io, iter, sync::Arc, task::Poll};
+
+use futures::{
+	SinkExt, StreamExt,
+	channel::mpsc,
+	future::{BoxFuture, FutureExt},
+	stream::FuturesUnordered,
+};
+use rs_mojave_transport_node::{AsyncReadWrite, ConnectionEvent, ProtocolHandler, ProtocolHandlerEvent, ProtocolInfo, StreamProtocol};
+
+use crate::{Codec, Config, Error, protocol};
+
+/// How many responses we buffer on the application's behalf before the handler's write loop
+/// applies backpressure.
+const RESPONSE_CHANNEL_CAPACITY: usize = 16;
+
+type BoxedStream = Box<dyn AsyncReadWrite + Send + Unpin>;
+
+/// A request queued by [`crate::StreamingResponse::request`], waiting for an outbound substream
+/// to carry it.
+pub(crate) struct OutboundRequest<C: Codec> {
+	pub(crate) request: C::Request,
+	pub(crate) responses: mpsc::Sender<C::Response>,
+}
+
+/// A command delivered to a single connection's [`Handler`] by the owning
+/// [`crate::StreamingResponse`].
+pub enum Command<C: Codec> {
+	SendRequest(OutboundRequest<C>),
+}
+
+impl<C: Codec> fmt::Debug for Command<C> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Command::SendRequest(_) => f.debug_tuple("SendRequest").finish_non_exhaustive(),
+		}
+	}
+}
+
+/// Notifications a [`Handler`] reports back up to [`crate::StreamingResponse`].
+pub enum Event<C: Codec> {
+	/// A remote peer opened a substream and sent a request. The application drains its responses
+	/// into `responses`; dropping `responses` sends the end-of-stream marker.
+	InboundRequest {
+		request: C::Request,
+		responses: mpsc::Sender<C::Response>,
+	},
+	/// An outbound request queued via [`crate::StreamingResponse::request`] failed. Response
+	/// frames that already arrived were forwarded to the caller's channel before this is reported.
+	OutboundRequestFailed(Error),
+}
+
+impl<C: Codec> fmt::Debug for Event<C> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Event::InboundRequest { .. } => f.debug_struct("InboundRequest").finish_non_exhaustive(),
+			Event::OutboundRequestFailed(e) => f.debug_tuple("OutboundRequestFailed").field(e).finish(),
+		}
+	}
+}
+
+/// Sends the request on a freshly opened outbound substream, then relays every response frame
+/// into `responses` until the peer sends the end-of-stream marker.
+async fn run_outbound<C: Codec>(codec: Arc<C>, mut stream: BoxedStream, outbound: OutboundRequest<C>) -> Result<(), Error> {
+	let OutboundRequest { request, mut responses } = outbound;
+
+	let payload = codec.encode_request(&request).map_err(Error::Codec)?;
+	protocol::write_frame(&mut stream, &payload).await?;
+
+	while let Some(bytes) = protocol::read_frame(&mut stream).await? {
+		let response = codec.decode_response(&bytes).map_err(Error::Codec)?;
+		if responses.send(response).await.is_err() {
+			// The caller dropped its receiver; stop relaying, but let the substream close
+			// naturally rather than aborting mid-frame.
+			break;
+		}
+	}
+
+	Ok(())
+}
+
+/// Drains the application's responses onto the wire, length-prefixing each one, then writes the
+/// end-of-stream marker once `responses` is exhausted (including when the sender is dropped).
+async fn drain_responses<C: Codec>(codec: Arc<C>, mut stream: BoxedStream, mut responses: mpsc::Receiver<C::Response>) {
+	while let Some(response) = responses.next().await {
+		let payload = match codec.encode_response(&response) {
+			Ok(bytes) => bytes,
+			Err(_) => break,
+		};
+		if protocol::write_frame(&mut stream, &payload).await.is_err() {
+			return;
+		}
+	}
+
+	let _ = protocol::write_end_of_stream(&mut stream).await;
+}
+
+pub struct Handler<C: Codec> {
+	codec: Arc<C>,
+	protocol_name: StreamProtocol,
+
+	/// Requests that have not yet been handed an outbound substream.
+	pending_requests: VecDeque<OutboundRequest<C>>,
+	/// Number of [`ProtocolHandlerEvent::OutboundSubstreamRequest`]s we've emitted that haven't
+	/// been fulfilled by a matching [`ConnectionEvent::NewOutboundStream`] yet.
+	outbound_streams_requested: usize,
+	/// Outbound substreams currently writing their request and relaying response frames.
+	outbound: FuturesUnordered<BoxFuture<'static, Result<(), Error>>>,
+
+	/// Inbound substreams currently reading and decoding their request frame.
+	inbound_reading: FuturesUnordered<BoxFuture<'static, io::Result<(BoxedStream, Vec<u8>)>>>,
+	/// Inbound substreams currently relaying the application's responses onto the wire.
+	inbound_writing: FuturesUnordered<BoxFuture<'static, ()>>,
+
+	pending_events: VecDeque<Event<C>>,
+	pending_errors: VecDeque<Error>,
+
+	/// Set once `poll_close` has been called; stops accepting new work.
+	closing: bool,
+}
+
+impl<C: Codec> Handler<C> {
+	pub fn new(config: Config, codec: Arc<C>) -> Self {
+		Self {
+			codec,
+			protocol_name: config.protocol_name().clone(),
+			pending_requests: VecDeque::new(),
+			outbound_streams_requested: 0,
+			outbound: FuturesUnordered::new(),
+			inbound_reading: FuturesUnordered::new(),
+			inbound_writing: FuturesUnordered::new(),
+			pending_events: VecDeque::new(),
+			pending_errors: VecDeque::new(),
+			closing: false,
+		}
+	}
+}
+
+impl<C: Codec> ProtocolHandler for Handler<C> {
+	type FromProtocol = Command<C>;
+	type ToProtocol = Event<C>;
+	type ProtocolInfoIter = iter::Once<ProtocolInfo>;
+
+	fn protocol_info(&self) -> Self::ProtocolInfoIter {
+		iter::once(ProtocolInfo::Exact(self.protocol_name.clone()))
+	}
+
+	fn on_protocol_event(&mut self, event: Self::FromProtocol) {
+		match event {
+			Command::SendRequest(outbound) => self.pending_requests.push_back(outbound),
+		}
+	}
+
+	fn on_connection_event(&mut self, event: ConnectionEvent) {
+		match event {
+			ConnectionEvent::NewOutboundStream(_protocol, stream) => {
+				self.outbound_streams_requested = self.outbound_streams_requested.saturating_sub(1);
+				if let Some(outbound) = self.pending_requests.pop_front() {
+					self.outbound.push(run_outbound(self.codec.clone(), stream, outbound).boxed());
+				}
+			}
+			ConnectionEvent::NewInboundStream(_protocol, stream) => {
+				if self.closing {
+					// Draining: don't accept new work on a handler that's shutting down.
+					return;
+				}
+				self.inbound_reading.push(protocol::read_request(stream).boxed());
+			}
+			ConnectionEvent::FailNegotiation(err) => {
+				self.outbound_streams_requested = self.outbound_streams_requested.saturating_sub(1);
+				// Dropping the request drops its `responses` sender, which closes the caller's channel.
+				self.pending_requests.pop_front();
+				let error = match err {
+					rs_mojave_transport_node::negotiator::NegotiatorStreamError::Timeout => {
+						Error::Io(io::Error::new(io::ErrorKind::TimedOut, "streaming-response negotiation timed out"))
+					}
+					rs_mojave_transport_node::negotiator::NegotiatorStreamError::IoError(error) => Error::Io(error),
+					rs_mojave_transport_node::negotiator::NegotiatorStreamError::NegotiationFailed => {
+						Error::UnsupportedProtocol
+					}
+				};
+				self.pending_errors.push_back(error);
+			}
+			ConnectionEvent::AddressChange(_) => {}
+		}
+	}
+
+	#[tracing::instrument(level = "debug", name = "StreamingResponseHandler::poll", skip(cx, self))]
+	fn poll(&mut self, cx: &mut std::task::Context<'_>) -> Poll<ProtocolHandlerEvent<Self::ToProtocol>> {
+		loop {
+			if let Some(error) = self.pending_errors.pop_front() {
+				tracing::error!("Handler::poll: {:?}", error);
+				return Poll::Ready(ProtocolHandlerEvent::NotifyProtocol(Event::OutboundRequestFailed(error)));
+			}
+
+			if let Some(event) = self.pending_events.pop_front() {
+				return Poll::Ready(ProtocolHandlerEvent::NotifyProtocol(event));
+			}
+
+			match self.outbound.poll_next_unpin(cx) {
+				Poll::Ready(Some(Err(error))) => {
+					self.pending_errors.push_back(error);
+					continue;
+				}
+				Poll::Ready(Some(Ok(()))) => continue,
+				Poll::Ready(None) | Poll::Pending => {}
+			}
+
+			match self.inbound_reading.poll_next_unpin(cx) {
+				Poll::Ready(Some(Ok((stream, bytes)))) => {
+					match self.codec.decode_request(&bytes) {
+						Ok(request) => {
+							let (responses_tx, responses_rx) = mpsc::channel(RESPONSE_CHANNEL_CAPACITY);
+							self.inbound_writing
+								.push(drain_responses(self.codec.clone(), stream, responses_rx).boxed());
+							self.pending_events.push_back(Event::InboundRequest {
+								request,
+								responses: responses_tx,
+							});
+						}
+						Err(error) => tracing::debug!(?error, "Handler::poll: failed to decode inbound request"),
+					}
+					continue;
+				}
+				Poll::Ready(Some(Err(error))) => {
+					tracing::debug!(?error, "Handler::poll: failed to read inbound request");
+					continue;
+				}
+				Poll::Ready(None) | Poll::Pending => {}
+			}
+
+			match self.inbound_writing.poll_next_unpin(cx) {
+				Poll::Ready(Some(())) => continue,
+				Poll::Ready(None) | Poll::Pending => {}
+			}
+
+			if !self.closing && self.pending_requests.len() > self.outbound_streams_requested {
+				self.outbound_streams_requested += 1;
+				return Poll::Ready(ProtocolHandlerEvent::OutboundSubstreamRequest);
+			}
+
+			return Poll::Pending;
+		}
+	}
+
+	fn poll_close(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Option<Self::ToProtocol>> {
+		self.closing = true;
+
+		match self.poll(cx) {
+			Poll::Ready(ProtocolHandlerEvent::NotifyProtocol(event)) => Poll::Ready(Some(event)),
+			Poll::Ready(ProtocolHandlerEvent::OutboundSubstreamRequest) => {
+				unreachable!("poll() must not request a new outbound substream while closing")
+			}
+			Poll::Pending => {
+				let idle = self.outbound.is_empty() && self.inbound_reading.is_empty() && self.inbound_writing.is_empty();
+				if idle {
+					Poll::Ready(None)
+				} else {
+					Poll::Pending
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,99 @@
+use std::{collections::VecDeque, sync::Arc, task::Poll};
+
+use futures::channel::mpsc;
+use multiaddr::PeerId;
+use rs_mojave_transport_node::{Action, ConnectionId, FromNode, NotifyTarget, PeerProtocol};
+
+mod codec;
+mod config;
+mod error;
+mod handler;
+mod protocol;
+
+use crate::handler::{Command, Handler, OutboundRequest};
+
+pub use codec::Codec;
+pub use config::Config;
+pub use error::Error;
+pub use handler::Event;
+
+/// Number of response frames we buffer on the requester's behalf before the driving substream
+/// applies backpressure.
+const RESPONSE_CHANNEL_CAPACITY: usize = 16;
+
+/// A request/streaming-response [`PeerProtocol`], analogous to the `ping` protocol but for
+/// exchanges where a single request yields many ordered response frames -- paginated queries,
+/// subscriptions, and the like.
+pub struct StreamingResponse<C: Codec> {
+	config: Config,
+	codec: Arc<C>,
+
+	/// Requests queued via [`StreamingResponse::request`], waiting to be handed to the target
+	/// peer's [`Handler`] the next time it's polled. Keyed by the target peer.
+	pending_commands: VecDeque<(PeerId, Command<C>)>,
+
+	events: VecDeque<Event<C>>,
+}
+
+impl<C: Codec> StreamingResponse<C> {
+	pub fn new(config: Config, codec: C) -> Self {
+		Self {
+			config,
+			codec: Arc::new(codec),
+			pending_commands: VecDeque::new(),
+			events: VecDeque::new(),
+		}
+	}
+
+	/// Sends `req` to `peer` and returns a channel that yields its response frames in the order
+	/// the responder sent them. The channel closes once the responder sends the end-of-stream
+	/// marker, the request fails, or the connection to `peer` is lost.
+	pub fn request(&mut self, peer: PeerId, req: C::Request) -> mpsc::Receiver<C::Response> {
+		let (responses_tx, responses_rx) = mpsc::channel(RESPONSE_CHANNEL_CAPACITY);
+		self.pending_commands.push_back((
+			peer,
+			Command::SendRequest(OutboundRequest {
+				request: req,
+				responses: responses_tx,
+			}),
+		));
+		responses_rx
+	}
+}
+
+impl<C: Codec> PeerProtocol for StreamingResponse<C> {
+	type ToNode = Event<C>;
+
+	type Handler = Handler<C>;
+
+	#[tracing::instrument(level = "debug", name = "StreamingResponse::OnNewConnection", skip(self))]
+	fn on_new_connection(
+		&mut self,
+		_connection_id: ConnectionId,
+		_peer_id: PeerId,
+		_remote_addr: &multiaddr::Multiaddr,
+		_local_addr: Option<&multiaddr::Multiaddr>,
+	) -> Result<Self::Handler, rs_mojave_transport_node::ConnectionError> {
+		Ok(Handler::new(self.config.clone(), self.codec.clone()))
+	}
+
+	fn on_node_event(&mut self, _: FromNode) {}
+
+	fn poll(
+		&mut self,
+		_: &mut std::task::Context<'_>,
+	) -> Poll<Action<Self::ToNode, rs_mojave_transport_node::THandlerFromEvent<Self>>> {
+		if let Some(event) = self.events.pop_front() {
+			return Poll::Ready(Action::Event(event));
+		}
+
+		if let Some((peer, command)) = self.pending_commands.pop_front() {
+			return Poll::Ready(Action::Notify {
+				target: NotifyTarget::Peer(peer),
+				event: command,
+			});
+		}
+
+		Poll::Pending
+	}
+}
@@ -0,0 +1,67 @@
+use std::io;
+
+use futures::prelude::*;
+
+/// Sentinel length value that terminates a response substream in place of a real frame.
+const END_OF_STREAM: u32 = u32::MAX;
+
+/// Generous upper bound on a single frame, so a malformed or malicious peer can't make us
+/// allocate an unbounded buffer while reading the length prefix.
+pub const MAX_FRAME_LEN: u32 = 16 * 1024 * 1024;
+
+/// Writes a single length-prefixed frame.
+pub(crate) async fn write_frame<S>(stream: &mut S, payload: &[u8]) -> io::Result<()>
+where
+	S: AsyncWrite + Unpin,
+{
+	let len = u32::try_from(payload.len())
+		.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame exceeds u32::MAX bytes"))?;
+	stream.write_all(&len.to_be_bytes()).await?;
+	stream.write_all(payload).await?;
+	stream.flush().await
+}
+
+/// Writes the end-of-stream marker that terminates a run of response frames.
+pub(crate) async fn write_end_of_stream<S>(stream: &mut S) -> io::Result<()>
+where
+	S: AsyncWrite + Unpin,
+{
+	stream.write_all(&END_OF_STREAM.to_be_bytes()).await?;
+	stream.flush().await
+}
+
+/// Reads the next frame, or `None` once the end-of-stream marker is reached.
+pub(crate) async fn read_frame<S>(stream: &mut S) -> io::Result<Option<Vec<u8>>>
+where
+	S: AsyncRead + Unpin,
+{
+	let mut len_buf = [0u8; 4];
+	stream.read_exact(&mut len_buf).await?;
+	let len = u32::from_be_bytes(len_buf);
+	if len == END_OF_STREAM {
+		return Ok(None);
+	}
+	if len > MAX_FRAME_LEN {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("frame of {len} bytes exceeds the {MAX_FRAME_LEN} byte limit"),
+		));
+	}
+	let mut payload = vec![0u8; len as usize];
+	stream.read_exact(&mut payload).await?;
+	Ok(Some(payload))
+}
+
+/// Reads the single request frame a freshly opened inbound substream starts with.
+pub(crate) async fn read_request<S>(mut stream: S) -> io::Result<(S, Vec<u8>)>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	match read_frame(&mut stream).await? {
+		Some(bytes) => Ok((stream, bytes)),
+		None => Err(io::Error::new(
+			io::ErrorKind::UnexpectedEof,
+			"peer closed the substream before sending a request",
+		)),
+	}
+}
@@ -3,7 +3,7 @@ use clap::Parser;
 use futures::StreamExt;
 use libp2p_identity::Keypair;
 use moq_native::server;
-use rs_mojave_network_core::{muxing::StreamMuxerBox, transport::Transport};
+use rs_mojave_network_core::{muxing::StreamMuxerBox, transport::{Transport, TransportExt}};
 use rs_mojave_transport_node::Builder;
 
 #[derive(Parser, Clone)]
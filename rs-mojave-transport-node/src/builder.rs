@@ -6,7 +6,7 @@ use rs_mojave_network_core::{
 };
 use std::collections::{HashMap, hash_map::Entry};
 
-use crate::{Node, PeerProtocol, error::BuilderError};
+use crate::{ConnectionLimits, Executor, Metrics, Node, PeerProtocol, error::BuilderError};
 
 pub struct BuildableStep;
 
@@ -25,6 +25,9 @@ pub struct BuildingState;
 pub struct Builder<Step = BuildingStep, T = BuildingState> {
 	keypair: libp2p_identity::Keypair,
 	transports: HashMap<Protocol, transport::Boxed<(PeerId, StreamMuxerBox)>>,
+	executor: Option<Box<dyn Executor + Send>>,
+	metrics: Option<Metrics>,
+	limits: ConnectionLimits,
 	_step: std::marker::PhantomData<Step>,
 	state: T,
 }
@@ -34,6 +37,9 @@ impl Builder {
 		Self {
 			keypair,
 			transports: HashMap::new(),
+			executor: None,
+			metrics: None,
+			limits: ConnectionLimits::default(),
 			_step: Default::default(),
 			state: Default::default(),
 		}
@@ -57,6 +63,28 @@ impl Builder<BuildingStep, BuildingState> {
 		}
 	}
 
+	/// Runs the node's connection and peer tasks on `executor` instead of the process-default one
+	/// from [`crate::executor::get_executor`] (tokio-backed when the `tokio` feature is enabled,
+	/// otherwise [`crate::executor::InlineExecutor`]). Lets the crate run under async-std, smol, or a single-threaded/wasm
+	/// executor where `tokio::spawn` isn't available.
+	pub fn with_executor(mut self, executor: impl Executor + Send + 'static) -> Self {
+		self.executor = Some(Box::new(executor));
+		self
+	}
+
+	/// Registers connection/negotiation metrics into `registry` and records them from the node's
+	/// event points going forward. See [`crate::Metrics`] for what's tracked.
+	pub fn with_metrics(mut self, registry: &mut prometheus_client::registry::Registry) -> Self {
+		self.metrics = Some(Metrics::new(registry));
+		self
+	}
+
+	/// Sets the [`ConnectionLimits`] the built node enforces. Defaults to unlimited.
+	pub fn with_limits(mut self, limits: ConnectionLimits) -> Self {
+		self.limits = limits;
+		self
+	}
+
 	pub fn with_protocol<P: PeerProtocol, R: TryIntoPeerProtocol<P> + PeerProtocol>(
 		self,
 		constructor: impl FnOnce(&libp2p_identity::Keypair) -> R,
@@ -66,6 +94,9 @@ impl Builder<BuildingStep, BuildingState> {
 		Ok(Builder {
 			keypair: self.keypair,
 			transports: self.transports,
+			executor: self.executor,
+			metrics: self.metrics,
+			limits: self.limits,
 			_step: std::marker::PhantomData::<BuildableStep>,
 			state: ProtocolsState { protocols },
 		})
@@ -81,6 +112,9 @@ where
 			self.keypair.public().to_peer_id(),
 			self.state.protocols,
 			self.transports,
+			self.executor,
+			self.metrics,
+			self.limits,
 		)
 	}
 }
@@ -1,36 +1,32 @@
 use parking_lot::Mutex;
 use slab::Slab;
-use std::{
-	fmt::{Debug, Display},
-	sync::LazyLock,
-};
+use std::fmt::{Debug, Display};
 
+/// Identifies a single connection attempt or established connection. Carries a generation
+/// alongside the slab index it was allocated from (see [`ConnectionIdAllocator`]), so that a slot
+/// reused after [`ConnectionIdAllocator::remove`] never produces an id equal to the previous
+/// occupant's.
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
-pub struct ConnectionId(usize);
-
-static CONNECTION_ID_ALLOCATOR: LazyLock<Mutex<Slab<()>>> = LazyLock::new(|| Mutex::new(Slab::new()));
+pub struct ConnectionId {
+	index: usize,
+	generation: u64,
+}
 
 impl ConnectionId {
-	pub fn next() -> Self {
-		let mut slab = CONNECTION_ID_ALLOCATOR.lock();
-		ConnectionId(slab.insert(()))
-	}
-
-	pub fn remove(self) {
-		let mut slab = CONNECTION_ID_ALLOCATOR.lock();
-		slab.remove(self.0);
+	fn new(index: usize, generation: u64) -> Self {
+		Self { index, generation }
 	}
 }
 
 impl Debug for ConnectionId {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		write!(f, "ConnectionId({})", self.0)
+		write!(f, "ConnectionId({}.{})", self.index, self.generation)
 	}
 }
 
 impl Display for ConnectionId {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		write!(f, "{}", self.0)
+		write!(f, "{}.{}", self.index, self.generation)
 	}
 }
 
@@ -42,7 +38,7 @@ macro_rules! impl_connection_id_from_unsigned {
                     if val as u64 > usize::MAX as u64 {
                         panic!("Value too large for usize");
                     }
-                    ConnectionId(val as usize)
+                    ConnectionId::new(val as usize, 0)
                 }
             }
         )*
@@ -60,7 +56,7 @@ macro_rules! impl_connection_id_from_signed {
                     if val as u64 > usize::MAX as u64 {
                         panic!("Value too large for usize");
                     }
-                    ConnectionId(val as usize)
+                    ConnectionId::new(val as usize, 0)
                 }
             }
         )*
@@ -70,6 +66,64 @@ macro_rules! impl_connection_id_from_signed {
 impl_connection_id_from_unsigned!(u8, u16, u32, u64, usize);
 impl_connection_id_from_signed!(i8, i16, i32, i64, isize);
 
+/// Hands out [`ConnectionId`]s that are unique within this allocator. Owned by [`crate::peer::manager::Manager`] rather
+/// than shared as a global static, so independent [`crate::Node`]s in the same process each get
+/// their own id space and contend on their own lock instead of a process-wide one.
+///
+/// Each slab slot carries a generation counter that's bumped every time the slot is freed, so a
+/// [`ConnectionId`] handed out before a [`ConnectionIdAllocator::remove`] never compares equal to
+/// one handed out afterwards for the same reused slot -- closing the id-aliasing hole the previous
+/// bare-index design had.
+pub struct ConnectionIdAllocator {
+	inner: Mutex<AllocatorInner>,
+}
+
+struct AllocatorInner {
+	slots: Slab<()>,
+	generations: Vec<u64>,
+}
+
+impl ConnectionIdAllocator {
+	pub fn new() -> Self {
+		Self {
+			inner: Mutex::new(AllocatorInner {
+				slots: Slab::new(),
+				generations: Vec::new(),
+			}),
+		}
+	}
+
+	/// Allocates a fresh [`ConnectionId`].
+	pub fn next(&self) -> ConnectionId {
+		let mut inner = self.inner.lock();
+		let index = inner.slots.insert(());
+		if index == inner.generations.len() {
+			inner.generations.push(0);
+		}
+		ConnectionId::new(index, inner.generations[index])
+	}
+
+	/// Releases `id`'s slot for reuse, bumping its generation so a later [`ConnectionIdAllocator::next`]
+	/// reusing the same slot can't produce an id equal to `id`. Returns `false` without taking any
+	/// action if `id` is unknown or stale (already removed), rather than panicking.
+	pub fn remove(&self, id: ConnectionId) -> bool {
+		let mut inner = self.inner.lock();
+		if !inner.slots.contains(id.index) || inner.generations.get(id.index) != Some(&id.generation) {
+			return false;
+		}
+
+		inner.slots.remove(id.index);
+		inner.generations[id.index] = inner.generations[id.index].wrapping_add(1);
+		true
+	}
+}
+
+impl Default for ConnectionIdAllocator {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -78,7 +132,7 @@ mod tests {
 	#[test]
 	fn test_connection_id_creation() {
 		let id = ConnectionId::from(42usize);
-		assert_eq!(format!("{id:?}"), "ConnectionId(42)");
+		assert_eq!(format!("{id:?}"), "ConnectionId(42.0)");
 	}
 
 	#[test]
@@ -89,7 +143,7 @@ mod tests {
 
 		assert_eq!(from_usize, from_u32);
 		assert_eq!(from_u32, from_u64);
-		assert_eq!(format!("{from_usize:?}"), "ConnectionId(123)");
+		assert_eq!(format!("{from_usize:?}"), "ConnectionId(123.0)");
 	}
 
 	#[test]
@@ -119,8 +173,8 @@ mod tests {
 
 		assert_eq!(id1, id2);
 		// Both should still be usable after copy
-		assert_eq!(format!("{id1:?}"), "ConnectionId(42)");
-		assert_eq!(format!("{id2:?}"), "ConnectionId(42)");
+		assert_eq!(format!("{id1:?}"), "ConnectionId(42.0)");
+		assert_eq!(format!("{id2:?}"), "ConnectionId(42.0)");
 	}
 
 	#[test]
@@ -167,9 +221,9 @@ mod tests {
 		let id_max = ConnectionId::from(usize::MAX);
 		let id_random = ConnectionId::from(12345);
 
-		assert_eq!(format!("{id_zero:?}"), "ConnectionId(0)");
-		assert_eq!(format!("{id_max:?}"), format!("ConnectionId({})", usize::MAX));
-		assert_eq!(format!("{id_random:?}"), "ConnectionId(12345)");
+		assert_eq!(format!("{id_zero:?}"), "ConnectionId(0.0)");
+		assert_eq!(format!("{id_max:?}"), format!("ConnectionId({}.0)", usize::MAX));
+		assert_eq!(format!("{id_random:?}"), "ConnectionId(12345.0)");
 	}
 
 	#[test]
@@ -177,16 +231,17 @@ mod tests {
 		let id_zero = ConnectionId::from(0);
 		let id_max = ConnectionId::from(usize::MAX);
 
-		assert_eq!(format!("{id_zero:?}"), "ConnectionId(0)");
-		assert_eq!(format!("{id_max:?}"), format!("ConnectionId({})", usize::MAX));
+		assert_eq!(format!("{id_zero:?}"), "ConnectionId(0.0)");
+		assert_eq!(format!("{id_max:?}"), format!("ConnectionId({}.0)", usize::MAX));
 		assert_ne!(id_zero, id_max);
 	}
 
 	#[test]
 	fn test_connection_id_next() {
-		let id1 = ConnectionId::next();
-		let id2 = ConnectionId::next();
-		let id3 = ConnectionId::next();
+		let allocator = ConnectionIdAllocator::new();
+		let id1 = allocator.next();
+		let id2 = allocator.next();
+		let id3 = allocator.next();
 
 		// Each call to next() should return a unique ID
 		assert_ne!(id1, id2);
@@ -201,18 +256,21 @@ mod tests {
 
 	#[test]
 	fn test_connection_id_remove() {
-		let id = ConnectionId::next();
+		let allocator = ConnectionIdAllocator::new();
+		let id = allocator.next();
 
 		// Remove should succeed
-		id.remove();
+		assert!(allocator.remove(id));
 	}
 
 	#[test]
 	fn test_connection_id_next_and_remove_cycle() {
+		let allocator = ConnectionIdAllocator::new();
+
 		// Allocate some IDs
-		let id1 = ConnectionId::next();
-		let id2 = ConnectionId::next();
-		let id3 = ConnectionId::next();
+		let id1 = allocator.next();
+		let id2 = allocator.next();
+		let id3 = allocator.next();
 
 		// All should be different
 		assert_ne!(id1, id2);
@@ -220,11 +278,11 @@ mod tests {
 		assert_ne!(id1, id3);
 
 		// Remove one of them
-		id2.remove();
+		assert!(allocator.remove(id2));
 
 		// Allocate more IDs
-		let id4 = ConnectionId::next();
-		let id5 = ConnectionId::next();
+		let id4 = allocator.next();
+		let id5 = allocator.next();
 
 		// New IDs should be different from existing ones
 		assert_ne!(id4, id1);
@@ -234,40 +292,44 @@ mod tests {
 		assert_ne!(id4, id5);
 
 		// Clean up
-		id1.remove();
-		id3.remove();
-		id4.remove();
-		id5.remove();
+		assert!(allocator.remove(id1));
+		assert!(allocator.remove(id3));
+		assert!(allocator.remove(id4));
+		assert!(allocator.remove(id5));
 	}
 
 	#[test]
 	fn test_connection_id_reuse_after_remove() {
+		let allocator = ConnectionIdAllocator::new();
+
 		// Allocate and immediately remove an ID
-		let id1 = ConnectionId::next();
-		id1.remove();
+		let id1 = allocator.next();
+		assert!(allocator.remove(id1));
 
 		// The slab may reuse the slot, so let's allocate a few more
-		let id2 = ConnectionId::next();
-		let id3 = ConnectionId::next();
+		let id2 = allocator.next();
+		let id3 = allocator.next();
 
-		// They should be valid IDs
+		// They should be valid IDs, and a slot reused from id1 must not alias it
 		assert!(format!("{id2:?}").starts_with("ConnectionId("));
 		assert!(format!("{id3:?}").starts_with("ConnectionId("));
 		assert_ne!(id2, id3);
+		assert_ne!(id1, id2);
+		assert_ne!(id1, id3);
 
 		// Clean up
-		id2.remove();
-		id3.remove();
+		assert!(allocator.remove(id2));
+		assert!(allocator.remove(id3));
 	}
 
 	#[test]
 	fn test_connection_id_multiple_allocations() {
+		let allocator = ConnectionIdAllocator::new();
 		let mut ids = Vec::new();
 
 		// Allocate multiple IDs
 		for _ in 0..10 {
-			let id = ConnectionId::next();
-			ids.push(id);
+			ids.push(allocator.next());
 		}
 
 		// All IDs should be unique
@@ -281,19 +343,18 @@ mod tests {
 
 		// Remove all IDs
 		for id in ids {
-			id.remove();
+			assert!(allocator.remove(id));
 		}
 	}
 
 	#[test]
 	fn test_connection_id_multiple_next_calls() {
-		// Test allocating multiple IDs and ensure they're all unique
+		let allocator = ConnectionIdAllocator::new();
 		let mut allocated_ids = Vec::new();
 
 		// Allocate 5 IDs
 		for _ in 0..5 {
-			let id = ConnectionId::next();
-			allocated_ids.push(id);
+			allocated_ids.push(allocator.next());
 		}
 
 		// Verify all are unique
@@ -305,7 +366,7 @@ mod tests {
 
 		// Clean up all IDs
 		for id in allocated_ids {
-			id.remove();
+			assert!(allocator.remove(id));
 		}
 	}
 
@@ -313,55 +374,67 @@ mod tests {
 	fn test_connection_id_zero_value() {
 		// Test that we can handle a ConnectionId with value 0
 		let zero_id = ConnectionId::from(0usize);
-		assert_eq!(format!("{zero_id:?}"), "ConnectionId(0)");
+		assert_eq!(format!("{zero_id:?}"), "ConnectionId(0.0)");
 	}
 
 	#[test]
 	fn test_connection_id_allocator_consistency() {
+		let allocator = ConnectionIdAllocator::new();
+
 		// Test that the allocator maintains consistency across multiple operations
-		let id1 = ConnectionId::next();
-		let id2 = ConnectionId::next();
+		let id1 = allocator.next();
+		let id2 = allocator.next();
 
 		// IDs should be different
 		assert_ne!(id1, id2);
 
 		// Remove first ID
-		id1.remove();
+		assert!(allocator.remove(id1));
 
 		// Allocate another ID
-		let id3 = ConnectionId::next();
+		let id3 = allocator.next();
 
 		// New ID should be different from the one still allocated
 		assert_ne!(id2, id3);
 
 		// Clean up
-		id2.remove();
-		id3.remove();
+		assert!(allocator.remove(id2));
+		assert!(allocator.remove(id3));
 	}
 
 	#[test]
 	fn test_connection_id_remove_non_allocated() {
-		// Create a ConnectionId without using the allocator
+		let allocator = ConnectionIdAllocator::new();
+
+		// Create a ConnectionId without using this allocator
 		let fake_id = ConnectionId::from(999999usize);
 
-		// Removing a non-allocated ID should not panic with parking_lot mutex
-		// but slab.remove() will still panic on invalid indices
-		// This is expected behavior - we're testing that the mutex itself doesn't cause issues
-		std::panic::catch_unwind(|| {
-			fake_id.remove();
-		})
-		.expect_err("Should panic when trying to remove non-allocated ID");
+		// Removing an id the allocator never handed out is a no-op, not a panic.
+		assert!(!allocator.remove(fake_id));
+	}
+
+	#[test]
+	fn test_connection_id_remove_stale_generation() {
+		let allocator = ConnectionIdAllocator::new();
+
+		// Allocate and remove an id, then let the slot get reused.
+		let id1 = allocator.next();
+		assert!(allocator.remove(id1));
+		let id2 = allocator.next();
+
+		// id1 is stale now: its slot exists again, but under a new generation.
+		assert!(!allocator.remove(id1));
+		assert!(allocator.remove(id2));
 	}
 
 	#[test]
 	fn test_connection_id_stress_allocation() {
-		// Test allocating and deallocating many IDs to ensure robustness
+		let allocator = ConnectionIdAllocator::new();
 		let mut allocated_ids = Vec::new();
 
 		// Allocate 50 IDs
 		for _ in 0..50 {
-			let id = ConnectionId::next();
-			allocated_ids.push(id);
+			allocated_ids.push(allocator.next());
 		}
 
 		// Verify all are unique
@@ -374,26 +447,25 @@ mod tests {
 		// Remove every other ID
 		for (i, &id) in allocated_ids.iter().enumerate() {
 			if i % 2 == 0 {
-				id.remove();
+				assert!(allocator.remove(id));
 			}
 		}
 
 		// Allocate 25 more IDs (should reuse some slots)
 		let mut new_ids = Vec::new();
 		for _ in 0..25 {
-			let id = ConnectionId::next();
-			new_ids.push(id);
+			new_ids.push(allocator.next());
 		}
 
 		// Clean up remaining IDs
 		for (i, &id) in allocated_ids.iter().enumerate() {
 			if i % 2 == 1 {
-				id.remove();
+				assert!(allocator.remove(id));
 			}
 		}
 
 		for id in new_ids {
-			id.remove();
+			assert!(allocator.remove(id));
 		}
 	}
 
@@ -401,19 +473,20 @@ mod tests {
 	fn test_connection_id_concurrent_like_access() {
 		// Test that multiple rapid allocations and deallocations work correctly
 		// This simulates concurrent-like access patterns
+		let allocator = ConnectionIdAllocator::new();
 		let mut all_ids = Vec::new();
 
 		// Rapid allocation burst
 		for _ in 0..20 {
-			all_ids.push(ConnectionId::next());
+			all_ids.push(allocator.next());
 		}
 
 		// Interleaved removal and allocation
 		for i in 0..10 {
 			if i < all_ids.len() {
-				all_ids[i].remove();
+				assert!(allocator.remove(all_ids[i]));
 			}
-			all_ids.push(ConnectionId::next());
+			all_ids.push(allocator.next());
 		}
 
 		// Verify remaining IDs are unique
@@ -426,64 +499,68 @@ mod tests {
 
 		// Clean up
 		for id in remaining_ids {
-			id.remove();
+			assert!(allocator.remove(id));
 		}
 	}
 
 	#[test]
 	fn test_connection_id_slab_reuse_behavior() {
+		let allocator = ConnectionIdAllocator::new();
+
 		// Test that the slab correctly reuses slots after removal
-		let id1 = ConnectionId::next();
-		let id2 = ConnectionId::next();
-		let id3 = ConnectionId::next();
+		let id1 = allocator.next();
+		let id2 = allocator.next();
+		let id3 = allocator.next();
 
 		// Remove the middle ID
-		id2.remove();
+		assert!(allocator.remove(id2));
 
-		// Allocate a new ID - it should reuse the slot
-		let id4 = ConnectionId::next();
+		// Allocate a new ID - it should reuse the slot, under a new generation
+		let id4 = allocator.next();
 
-		// The new ID should be different from remaining allocated IDs
+		// The new ID should be different from remaining allocated IDs, and from id2
 		assert_ne!(id1, id4);
 		assert_ne!(id3, id4);
+		assert_ne!(id2, id4);
 
 		// Clean up
-		id1.remove();
-		id3.remove();
-		id4.remove();
+		assert!(allocator.remove(id1));
+		assert!(allocator.remove(id3));
+		assert!(allocator.remove(id4));
 	}
 
 	#[test]
 	fn test_connection_id_empty_and_refill() {
-		// Test allocating, clearing all, then allocating again
+		let allocator = ConnectionIdAllocator::new();
 		let mut first_batch = Vec::new();
 
 		// First batch of allocations
 		for _ in 0..10 {
-			first_batch.push(ConnectionId::next());
+			first_batch.push(allocator.next());
 		}
 
 		// Remove all
-		for id in first_batch {
-			id.remove();
+		for id in &first_batch {
+			assert!(allocator.remove(*id));
 		}
 
 		// Second batch of allocations
 		let mut second_batch = Vec::new();
 		for _ in 0..10 {
-			second_batch.push(ConnectionId::next());
+			second_batch.push(allocator.next());
 		}
 
-		// All should be unique
+		// All should be unique, and none alias the first batch
 		for i in 0..second_batch.len() {
 			for j in (i + 1)..second_batch.len() {
 				assert_ne!(second_batch[i], second_batch[j]);
 			}
+			assert!(!first_batch.contains(&second_batch[i]));
 		}
 
 		// Clean up
 		for id in second_batch {
-			id.remove();
+			assert!(allocator.remove(id));
 		}
 	}
 }
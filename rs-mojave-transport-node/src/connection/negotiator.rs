@@ -1,15 +1,47 @@
+use futures::{AsyncRead, AsyncWrite};
 use serde::{Deserialize, Serialize};
 
 use crate::StreamProtocol;
+use crate::protocol::ProtocolInfo;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamProtocols(pub Vec<StreamProtocol>);
 
+/// The local side's accepted protocol set, matched against a remote's proposed
+/// [`StreamProtocols`]. Unlike [`StreamProtocols`], this is never sent on the wire: a
+/// [`ProtocolInfo::Range`] entry is resolved against whichever concrete candidate the remote
+/// actually proposed, via [`crate::stream_protocol::StreamProtocolMatcher::matches`], rather than
+/// being transmitted as-is.
+#[derive(Debug, Clone)]
+pub struct AcceptedProtocols(pub Vec<ProtocolInfo>);
+
+impl AcceptedProtocols {
+	/// Returns `true` if `candidate` satisfies any entry in this set -- an exact match, or falls
+	/// within a [`ProtocolInfo::Range`]'s version requirement.
+	pub fn accepts(&self, candidate: &StreamProtocol) -> bool {
+		self.0.iter().any(|info| match info {
+			ProtocolInfo::Exact(protocol) => protocol == candidate,
+			ProtocolInfo::Range(matcher) => matcher.matches(candidate),
+		})
+	}
+}
+
+impl From<StreamProtocols> for AcceptedProtocols {
+	fn from(protocols: StreamProtocols) -> Self {
+		AcceptedProtocols(protocols.0.into_iter().map(ProtocolInfo::Exact).collect())
+	}
+}
+
 mod inbound;
+mod multistream_select_v1;
+mod negotiated;
 mod outbound;
+mod sim_open;
 
 pub use inbound::*;
+pub use negotiated::*;
 pub use outbound::*;
+pub use sim_open::*;
 
 #[derive(Debug, thiserror::Error)]
 pub enum NegotiatorStreamError {
@@ -21,4 +53,180 @@ pub enum NegotiatorStreamError {
 
 	#[error("Negotiation failed")]
 	NegotiationFailed,
+
+	#[error("No mutually supported protocol")]
+	UnsupportedProtocol,
+
+	#[error("Negotiation message exceeded the configured size limit")]
+	FrameTooLarge,
+
+	#[error("Malformed protocol data in negotiation message")]
+	InvalidProtocol,
+}
+
+/// Selects which handshake runs on a substream before multistream-select protocol negotiation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+	/// Plain multistream-select negotiation.
+	#[default]
+	V1,
+	/// Run the `/libp2p/simultaneous-connect` role-arbitration handshake (see
+	/// [`SimOpenStream`]) before multistream-select negotiation, so that a connection raced by
+	/// both peers dialing each other at once (e.g. NAT hole punching) ends up with a single
+	/// agreed initiator.
+	V1SimOpen,
+}
+
+/// Which multistream-select role this side takes: the dialer drives [`OutboundStream`], the
+/// listener drives [`InboundStream`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+	Dialer,
+	Listener,
+}
+
+/// The wire format used to actually carry protocol proposals once a [`Role`] has been decided.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NegotiationFormat {
+	/// This crate's own length-prefixed JSON exchange of the full [`StreamProtocols`] list.
+	/// Simple and cheap, but not understood by any other multistream-select implementation.
+	#[default]
+	Json,
+	/// The canonical multistream-select 1.0.0 wire format (see
+	/// <https://github.com/multiformats/multistream-select>): an unsigned-varint/newline-framed
+	/// header exchange followed by one proposed protocol per round trip, interoperable with
+	/// rust-libp2p and other multistream-select peers.
+	MultistreamSelectV1,
+}
+
+/// Whether [`OutboundStream`] waits for the listener's reply before resolving, or resolves
+/// optimistically as soon as its proposal has been flushed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NegotiationTiming {
+	/// Wait for the listener's reply before resolving -- the safe default.
+	#[default]
+	Eager,
+	/// Resolve as soon as a single proposed protocol has been flushed, without waiting for the
+	/// listener's reply. Only takes effect when exactly one protocol is proposed; saves a round
+	/// trip on fresh substreams where the responder is overwhelmingly likely to support the
+	/// requested protocol. The reply is still checked, transparently, on the first read of the
+	/// returned [`Negotiated`] stream -- a rejection or mismatch there surfaces as
+	/// [`NegotiatorStreamError::UnsupportedProtocol`] instead of application data.
+	Lazy,
+}
+
+/// Cap on a single negotiation message (a proposal list, header, or reply), in the spirit of
+/// devp2p's `MAX_PAYLOAD_SIZE`: generous enough for any realistic protocol list. The
+/// multistream-select 1.0.0 format checks a message's length prefix against the cap before reading
+/// its body; the JSON format reads the whole length-prefixed frame first and then rejects it if it
+/// is over the cap.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// Tunables for [`negotiate`]: the wire [`NegotiationFormat`] and [`NegotiationTiming`] to use, and
+/// the maximum size of a single negotiation message before it's rejected with
+/// [`NegotiatorStreamError::FrameTooLarge`] instead of being decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NegotiationConfig {
+	pub format: NegotiationFormat,
+	pub timing: NegotiationTiming,
+	pub max_message_size: usize,
+}
+
+impl Default for NegotiationConfig {
+	fn default() -> Self {
+		Self {
+			format: NegotiationFormat::default(),
+			timing: NegotiationTiming::default(),
+			max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+		}
+	}
+}
+
+/// Runs multistream-select negotiation over `stream`, proposing `propose` if we end up dialing or
+/// matching incoming proposals against `accept` if we end up listening. Returns the negotiated
+/// stream together with the [`Role`] this side ended up taking (since under
+/// [`Version::V1SimOpen`] that isn't necessarily `default_role`) and the single [`StreamProtocol`]
+/// both sides agreed on, so the caller can dispatch to the right [`crate::protocol::ProtocolHandler`]
+/// instead of guessing.
+///
+/// `propose` and `accept` are usually derived from the same [`crate::protocol::ProtocolHandler::protocol_info`]
+/// list: a dialer must offer concrete protocol strings, so `propose` only makes sense built from
+/// [`crate::protocol::ProtocolInfo::Exact`] entries, while `accept` can also carry
+/// [`crate::protocol::ProtocolInfo::Range`] entries, matched against whatever concrete protocol
+/// the dialer actually proposed -- see [`AcceptedProtocols`].
+///
+/// Plain dial/listen connections pass [`Version::V1`], which always negotiates as `default_role`
+/// -- today's behavior. A connection that may have been simultaneously opened by both peers (see
+/// [`rs_mojave_network_core::connection::ConnectionOrigin::SimultaneousOpen`]) should pass
+/// [`Version::V1SimOpen`] instead: a [`SimOpenStream`] handshake runs first to arbitrate a single
+/// initiator, and only its outcome -- not `default_role` -- decides which side then drives
+/// [`OutboundStream`] versus [`InboundStream`]. If the remote doesn't support simultaneous-open,
+/// arbitration falls back to `default_role`, same as [`Version::V1`].
+pub async fn negotiate<S>(
+	mode: Version,
+	default_role: Role,
+	config: NegotiationConfig,
+	propose: StreamProtocols,
+	accept: AcceptedProtocols,
+	stream: S,
+) -> Result<(Role, Negotiated<S>, StreamProtocol), NegotiatorStreamError>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let (role, stream) = match mode {
+		Version::V1 => (default_role, stream),
+		Version::V1SimOpen => match SimOpenStream::new(stream).await? {
+			SimOpenOutcome::Fallback(stream) => (default_role, stream),
+			SimOpenOutcome::Initiator(stream) => (Role::Dialer, stream),
+			SimOpenOutcome::Responder(stream) => (Role::Listener, stream),
+		},
+	};
+
+	let (stream, protocol) = match role {
+		Role::Dialer => OutboundStream::new(config, propose, stream).await?,
+		Role::Listener => {
+			let (stream, protocol) = InboundStream::new(config, accept, stream).await?;
+			(Negotiated::Completed(stream), protocol)
+		}
+	};
+
+	Ok((role, stream, protocol))
+}
+
+#[cfg(test)]
+mod tests {
+	use semver::VersionReq;
+
+	use super::*;
+	use crate::stream_protocol::StreamProtocolMatcher;
+
+	#[test]
+	fn accepted_protocols_matches_exact_entry() {
+		let exact = StreamProtocol::new("test", "protocol", "1.2.3".parse().unwrap());
+		let accepted = AcceptedProtocols(vec![ProtocolInfo::Exact(exact.clone())]);
+
+		assert!(accepted.accepts(&exact));
+		assert!(!accepted.accepts(&StreamProtocol::new("test", "protocol", "1.2.4".parse().unwrap())));
+	}
+
+	#[test]
+	fn accepted_protocols_matches_satisfying_range_entry() {
+		let matcher = StreamProtocolMatcher::new("test", "protocol", VersionReq::parse("^1.2").unwrap());
+		let accepted = AcceptedProtocols(vec![ProtocolInfo::Range(matcher)]);
+
+		let in_range = StreamProtocol::new("test", "protocol", "1.3.0".parse().unwrap());
+		let below_range = StreamProtocol::new("test", "protocol", "1.1.0".parse().unwrap());
+
+		assert!(accepted.accepts(&in_range));
+		assert!(!accepted.accepts(&below_range));
+	}
+
+	#[test]
+	fn accepted_protocols_from_stream_protocols_preserves_exact_matching() {
+		let protocol = StreamProtocol::new("test", "protocol", "1.0.0".parse().unwrap());
+		let accepted: AcceptedProtocols = StreamProtocols(vec![protocol.clone()]).into();
+
+		assert!(accepted.accepts(&protocol));
+		assert!(!accepted.accepts(&StreamProtocol::new("test", "protocol", "2.0.0".parse().unwrap())));
+	}
 }
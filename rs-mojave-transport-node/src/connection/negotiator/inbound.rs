@@ -12,13 +12,61 @@ use pin_project::pin_project;
 
 use crate::{
 	StreamProtocol,
-	connection::negotiator::{NegotiatorStreamError, StreamProtocols},
+	connection::negotiator::{AcceptedProtocols, NegotiationConfig, NegotiationFormat, NegotiatorStreamError, multistream_select_v1::Msv1InboundStream},
 };
 
-#[pin_project]
+/// Drives the listener side of multistream-select negotiation, in whichever [`NegotiationFormat`]
+/// was requested.
+#[pin_project(project = InboundStreamProj)]
 pub struct InboundStream<S> {
+	#[pin]
+	inner: Inner<S>,
+}
+
+#[pin_project(project = InnerProj)]
+enum Inner<S> {
+	Json(#[pin] JsonInboundStream<S>),
+	MultistreamSelectV1(#[pin] Msv1InboundStream<S>),
+}
+
+impl<S> InboundStream<S>
+where
+	S: AsyncWrite + AsyncRead + Unpin,
+{
+	pub(crate) fn new(config: NegotiationConfig, protocols: AcceptedProtocols, stream: S) -> Self {
+		let inner = match config.format {
+			NegotiationFormat::Json => Inner::Json(JsonInboundStream::new(config.max_message_size, protocols, stream)),
+			NegotiationFormat::MultistreamSelectV1 => {
+				Inner::MultistreamSelectV1(Msv1InboundStream::new(config.max_message_size, protocols, stream))
+			}
+		};
+
+		InboundStream { inner }
+	}
+}
+
+impl<S> Future for InboundStream<S>
+where
+	S: AsyncWrite + AsyncRead + Unpin,
+{
+	type Output = Result<(S, StreamProtocol), NegotiatorStreamError>;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		match self.project().inner.project() {
+			InnerProj::Json(s) => s.poll(cx),
+			InnerProj::MultistreamSelectV1(s) => s.poll(cx),
+		}
+	}
+}
+
+/// Receives the dialer's full [`StreamProtocols`] priority list as a single length-prefixed JSON
+/// message, picks the first entry [`AcceptedProtocols::accepts`], and replies with that single
+/// [`StreamProtocol`] (or `null` if none is accepted).
+#[pin_project]
+struct JsonInboundStream<S> {
 	timeout: Delay,
-	protocols: StreamProtocols,
+	max_message_size: usize,
+	protocols: AcceptedProtocols,
 	state: InboundState<S>,
 }
 
@@ -28,34 +76,35 @@ enum InboundState<S> {
 	},
 	SendProtocol {
 		io: Framed<S, LengthCodec>,
-		received_protocols: Option<Vec<StreamProtocol>>,
+		selected: Option<StreamProtocol>,
 	},
 	Flush {
 		io: Framed<S, LengthCodec>,
-		received_protocols: Option<Vec<StreamProtocol>>,
+		selected: Option<StreamProtocol>,
 	},
 	Done,
 }
 
-impl<S> InboundStream<S>
+impl<S> JsonInboundStream<S>
 where
 	S: AsyncWrite + AsyncRead + Unpin,
 {
-	pub(crate) fn new(protocols: StreamProtocols, stream: S) -> Self {
+	fn new(max_message_size: usize, protocols: AcceptedProtocols, stream: S) -> Self {
 		let framed = Framed::new(stream, LengthCodec);
-		InboundStream {
+		JsonInboundStream {
 			protocols,
+			max_message_size,
 			timeout: Delay::new(Duration::from_secs(15)),
 			state: InboundState::RecvProtocol { io: framed },
 		}
 	}
 }
 
-impl<S> Future for InboundStream<S>
+impl<S> Future for JsonInboundStream<S>
 where
 	S: AsyncWrite + AsyncRead + Unpin,
 {
-	type Output = Result<S, NegotiatorStreamError>;
+	type Output = Result<(S, StreamProtocol), NegotiatorStreamError>;
 
 	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
 		let this = self.project();
@@ -77,51 +126,50 @@ where
 						Poll::Ready(Some(Ok(msg))) => msg,
 					};
 
-					let received_protocols = serde_json::from_slice(msg.as_ref()).unwrap();
+					if msg.len() > *this.max_message_size {
+						return Poll::Ready(Err(NegotiatorStreamError::FrameTooLarge));
+					}
 
-					*this.state = InboundState::SendProtocol {
-						io,
-						received_protocols: Some(received_protocols),
+					let received_protocols: Vec<StreamProtocol> = match serde_json::from_slice(msg.as_ref()) {
+						Ok(protocols) => protocols,
+						Err(_) => return Poll::Ready(Err(NegotiatorStreamError::InvalidProtocol)),
 					};
+					let selected = received_protocols.into_iter().find(|p| this.protocols.accepts(p));
+					tracing::debug!(?selected, "selected negotiated protocol");
+
+					*this.state = InboundState::SendProtocol { io, selected };
 				}
-				InboundState::SendProtocol {
-					mut io,
-					received_protocols,
-				} => {
+				InboundState::SendProtocol { mut io, selected } => {
 					match Pin::new(&mut io).poll_ready(cx) {
 						Poll::Pending => {
-							*this.state = InboundState::SendProtocol { io, received_protocols };
+							*this.state = InboundState::SendProtocol { io, selected };
 							return Poll::Pending;
 						}
 						Poll::Ready(Ok(())) => {}
 						Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
 					}
 
-					let protocols_json = serde_json::to_vec(&this.protocols).unwrap();
-					if let Err(err) = Pin::new(&mut io).start_send(protocols_json.into()) {
+					let selected_json = match serde_json::to_vec(&selected) {
+						Ok(json) => json,
+						Err(_) => return Poll::Ready(Err(NegotiatorStreamError::InvalidProtocol)),
+					};
+					if let Err(err) = Pin::new(&mut io).start_send(selected_json.into()) {
 						return Poll::Ready(Err(NegotiatorStreamError::IoError(err)));
 					}
 
-					*this.state = InboundState::Flush { io, received_protocols };
+					*this.state = InboundState::Flush { io, selected };
 				}
-				InboundState::Flush {
-					mut io,
-					received_protocols,
-				} => match Pin::new(&mut io).poll_flush(cx) {
+				InboundState::Flush { mut io, selected } => match Pin::new(&mut io).poll_flush(cx) {
 					Poll::Pending => {
-						*this.state = InboundState::Flush { io, received_protocols };
+						*this.state = InboundState::Flush { io, selected };
 						return Poll::Pending;
 					}
-					Poll::Ready(Ok(())) => match received_protocols {
-						Some(protocols) => {
-							tracing::debug!(protocols=?protocols, "received protocols");
-							let inner = io.into_inner();
-							return Poll::Ready(Ok(inner));
-						}
-						None => {
-							*this.state = InboundState::RecvProtocol { io };
-						}
-					},
+					Poll::Ready(Ok(())) => {
+						return Poll::Ready(match selected {
+							Some(protocol) => Ok((io.into_inner(), protocol)),
+							None => Err(NegotiatorStreamError::UnsupportedProtocol),
+						});
+					}
 					Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
 				},
 				InboundState::Done => panic!("NegotiatorStream is in a done state"),
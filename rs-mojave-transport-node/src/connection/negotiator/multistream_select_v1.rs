@@ -0,0 +1,509 @@
+use std::{
+	collections::VecDeque,
+	io,
+	pin::Pin,
+	str::FromStr,
+	task::{Context, Poll},
+	time::Duration,
+};
+
+use asynchronous_codec::{Decoder, Encoder, Framed};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::{AsyncRead, AsyncWrite, FutureExt, Sink, Stream};
+use futures_timer::Delay;
+use pin_project::pin_project;
+
+use crate::{
+	StreamProtocol,
+	connection::negotiator::{AcceptedProtocols, Negotiated, NegotiationConfig, NegotiationTiming, NegotiatorStreamError, StreamProtocols},
+};
+
+/// The header both sides exchange before proposing protocols, identifying this as the canonical
+/// multistream-select 1.0.0 handshake (see <https://github.com/multiformats/multistream-select>).
+const HEADER: &[u8] = b"/multistream/1.0.0";
+
+/// The listener's reply to a proposal it doesn't support.
+const NOT_AVAILABLE: &[u8] = b"na";
+
+/// Frames multistream-select 1.0.0 messages: an unsigned-varint byte length (counting the
+/// trailing newline) followed by the payload and a terminating `\n`. Rejects incoming frames
+/// whose declared length exceeds `max_message_size` before buffering the rest of the payload.
+pub(crate) struct Msv1Codec {
+	max_message_size: usize,
+}
+
+impl Msv1Codec {
+	pub(crate) fn new(max_message_size: usize) -> Self {
+		Self { max_message_size }
+	}
+}
+
+impl Encoder<Bytes> for Msv1Codec {
+	type Error = io::Error;
+
+	fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+		write_varint(dst, item.len() as u64 + 1);
+		dst.extend_from_slice(&item);
+		dst.put_u8(b'\n');
+		Ok(())
+	}
+}
+
+impl Decoder for Msv1Codec {
+	type Item = Bytes;
+	type Error = io::Error;
+
+	fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, Self::Error> {
+		let Some((len, varint_len)) = read_varint(src) else {
+			return Ok(None);
+		};
+
+		if len > self.max_message_size {
+			return Err(io::Error::new(
+				io::ErrorKind::FileTooLarge,
+				format!("multistream-select message of {len} bytes exceeds the {} byte limit", self.max_message_size),
+			));
+		}
+
+		if src.len() < varint_len + len {
+			return Ok(None);
+		}
+
+		src.advance(varint_len);
+		let mut payload = src.split_to(len);
+		if payload.last() != Some(&b'\n') {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "multistream-select message missing newline"));
+		}
+		payload.truncate(payload.len() - 1);
+
+		Ok(Some(payload.freeze()))
+	}
+}
+
+/// Maps an I/O error from a [`Msv1Codec`]-framed stream into a [`NegotiatorStreamError`],
+/// recognizing the distinct [`io::ErrorKind::FileTooLarge`] the codec raises when a frame exceeds
+/// its configured size limit.
+fn map_codec_error(e: io::Error) -> NegotiatorStreamError {
+	if e.kind() == io::ErrorKind::FileTooLarge {
+		NegotiatorStreamError::FrameTooLarge
+	} else {
+		NegotiatorStreamError::IoError(e)
+	}
+}
+
+fn write_varint(dst: &mut BytesMut, mut value: u64) {
+	loop {
+		let byte = (value & 0x7f) as u8;
+		value >>= 7;
+		if value == 0 {
+			dst.put_u8(byte);
+			break;
+		}
+		dst.put_u8(byte | 0x80);
+	}
+}
+
+/// Reads an unsigned-varint length prefix from the front of `src`, returning the decoded value
+/// and the number of bytes it occupied, or `None` if `src` doesn't yet hold a complete varint.
+fn read_varint(src: &[u8]) -> Option<(usize, usize)> {
+	let mut value: u64 = 0;
+	for (i, &byte) in src.iter().enumerate().take(10) {
+		value |= u64::from(byte & 0x7f) << (7 * i);
+		if byte & 0x80 == 0 {
+			return Some((value as usize, i + 1));
+		}
+	}
+	None
+}
+
+/// Drives the dialer side of the canonical multistream-select 1.0.0 handshake: exchanges the
+/// `/multistream/1.0.0` header, then proposes each of `protocols` in priority order until the
+/// listener echoes one back (accepted) or every candidate has been rejected with `na`.
+#[pin_project]
+pub(crate) struct Msv1OutboundStream<S> {
+	timeout: Delay,
+	timing: NegotiationTiming,
+	/// Whether exactly one protocol was proposed, the precondition for [`NegotiationTiming::Lazy`]
+	/// to actually skip waiting for a reply.
+	single_candidate: bool,
+	max_message_size: usize,
+	remaining: VecDeque<StreamProtocol>,
+	state: OutboundState<S>,
+}
+
+enum OutboundState<S> {
+	SendHeader { io: Framed<S, Msv1Codec> },
+	FlushHeader { io: Framed<S, Msv1Codec> },
+	RecvHeader { io: Framed<S, Msv1Codec> },
+	SendProposal { io: Framed<S, Msv1Codec>, candidate: StreamProtocol },
+	FlushProposal { io: Framed<S, Msv1Codec>, candidate: StreamProtocol },
+	RecvReply { io: Framed<S, Msv1Codec>, candidate: StreamProtocol },
+	Done,
+}
+
+impl<S> Msv1OutboundStream<S>
+where
+	S: AsyncWrite + AsyncRead + Unpin,
+{
+	pub(crate) fn new(config: NegotiationConfig, protocols: StreamProtocols, stream: S) -> Self {
+		Msv1OutboundStream {
+			timeout: Delay::new(Duration::from_secs(15)),
+			timing: config.timing,
+			single_candidate: protocols.0.len() == 1,
+			max_message_size: config.max_message_size,
+			remaining: protocols.0.into(),
+			state: OutboundState::SendHeader { io: Framed::new(stream, Msv1Codec::new(config.max_message_size)) },
+		}
+	}
+}
+
+impl<S> Future for Msv1OutboundStream<S>
+where
+	S: AsyncWrite + AsyncRead + Unpin,
+{
+	type Output = Result<(Negotiated<S>, StreamProtocol), NegotiatorStreamError>;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let this = self.project();
+
+		if this.timeout.poll_unpin(cx).is_ready() {
+			return Poll::Ready(Err(NegotiatorStreamError::Timeout));
+		}
+
+		loop {
+			match std::mem::replace(this.state, OutboundState::Done) {
+				OutboundState::SendHeader { mut io } => {
+					match Pin::new(&mut io).poll_ready(cx) {
+						Poll::Pending => {
+							*this.state = OutboundState::SendHeader { io };
+							return Poll::Pending;
+						}
+						Poll::Ready(Ok(())) => {}
+						Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+					}
+
+					if let Err(e) = Pin::new(&mut io).start_send(Bytes::from_static(HEADER)) {
+						return Poll::Ready(Err(NegotiatorStreamError::IoError(e)));
+					}
+
+					*this.state = OutboundState::FlushHeader { io };
+				}
+
+				OutboundState::FlushHeader { mut io } => match Pin::new(&mut io).poll_flush(cx) {
+					Poll::Pending => {
+						*this.state = OutboundState::FlushHeader { io };
+						return Poll::Pending;
+					}
+					Poll::Ready(Ok(())) => *this.state = OutboundState::RecvHeader { io },
+					Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+				},
+
+				OutboundState::RecvHeader { mut io } => {
+					let msg = match Pin::new(&mut io).poll_next(cx) {
+						Poll::Pending => {
+							*this.state = OutboundState::RecvHeader { io };
+							return Poll::Pending;
+						}
+						Poll::Ready(None) => return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed)),
+						Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(map_codec_error(e))),
+						Poll::Ready(Some(Ok(msg))) => msg,
+					};
+
+					if msg.as_ref() != HEADER {
+						return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed));
+					}
+
+					match this.remaining.pop_front() {
+						Some(candidate) => *this.state = OutboundState::SendProposal { io, candidate },
+						None => return Poll::Ready(Err(NegotiatorStreamError::UnsupportedProtocol)),
+					}
+				}
+
+				OutboundState::SendProposal { mut io, candidate } => {
+					match Pin::new(&mut io).poll_ready(cx) {
+						Poll::Pending => {
+							*this.state = OutboundState::SendProposal { io, candidate };
+							return Poll::Pending;
+						}
+						Poll::Ready(Ok(())) => {}
+						Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+					}
+
+					if let Err(e) = Pin::new(&mut io).start_send(Bytes::copy_from_slice(candidate.as_ref().as_bytes())) {
+						return Poll::Ready(Err(NegotiatorStreamError::IoError(e)));
+					}
+
+					*this.state = OutboundState::FlushProposal { io, candidate };
+				}
+
+				OutboundState::FlushProposal { mut io, candidate } => match Pin::new(&mut io).poll_flush(cx) {
+					Poll::Pending => {
+						*this.state = OutboundState::FlushProposal { io, candidate };
+						return Poll::Pending;
+					}
+					Poll::Ready(Ok(())) => {
+						if *this.timing == NegotiationTiming::Lazy && *this.single_candidate {
+							tracing::debug!(protocol=?candidate, "multistream-select 1.0.0 proposed optimistically");
+							return Poll::Ready(Ok((
+								Negotiated::expecting_msv1(io.into_inner(), candidate.clone(), *this.max_message_size),
+								candidate,
+							)));
+						}
+						*this.state = OutboundState::RecvReply { io, candidate };
+					}
+					Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+				},
+
+				OutboundState::RecvReply { mut io, candidate } => {
+					let msg = match Pin::new(&mut io).poll_next(cx) {
+						Poll::Pending => {
+							*this.state = OutboundState::RecvReply { io, candidate };
+							return Poll::Pending;
+						}
+						Poll::Ready(None) => return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed)),
+						Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(map_codec_error(e))),
+						Poll::Ready(Some(Ok(msg))) => msg,
+					};
+
+					if msg.as_ref() == candidate.as_ref().as_bytes() {
+						tracing::debug!(protocol=?candidate, "multistream-select 1.0.0 negotiated");
+						return Poll::Ready(Ok((Negotiated::Completed(io.into_inner()), candidate)));
+					}
+
+					if msg.as_ref() != NOT_AVAILABLE {
+						return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed));
+					}
+
+					match this.remaining.pop_front() {
+						Some(next) => *this.state = OutboundState::SendProposal { io, candidate: next },
+						None => return Poll::Ready(Err(NegotiatorStreamError::UnsupportedProtocol)),
+					}
+				}
+
+				OutboundState::Done => panic!("Msv1OutboundStream polled after completion"),
+			}
+		}
+	}
+}
+
+/// Drives the listener side of the canonical multistream-select 1.0.0 handshake: exchanges the
+/// `/multistream/1.0.0` header, then echoes back whichever proposed protocol [`AcceptedProtocols::accepts`],
+/// rejecting every other proposal with `na` until the dialer gives up.
+#[pin_project]
+pub(crate) struct Msv1InboundStream<S> {
+	timeout: Delay,
+	protocols: AcceptedProtocols,
+	state: InboundState<S>,
+}
+
+enum InboundState<S> {
+	RecvHeader { io: Framed<S, Msv1Codec> },
+	SendHeader { io: Framed<S, Msv1Codec> },
+	FlushHeader { io: Framed<S, Msv1Codec> },
+	RecvProposal { io: Framed<S, Msv1Codec> },
+	SendReply { io: Framed<S, Msv1Codec>, selected: Option<StreamProtocol> },
+	FlushReply { io: Framed<S, Msv1Codec>, selected: Option<StreamProtocol> },
+	Done,
+}
+
+impl<S> Msv1InboundStream<S>
+where
+	S: AsyncWrite + AsyncRead + Unpin,
+{
+	pub(crate) fn new(max_message_size: usize, protocols: AcceptedProtocols, stream: S) -> Self {
+		Msv1InboundStream {
+			timeout: Delay::new(Duration::from_secs(15)),
+			protocols,
+			state: InboundState::RecvHeader { io: Framed::new(stream, Msv1Codec::new(max_message_size)) },
+		}
+	}
+}
+
+impl<S> Future for Msv1InboundStream<S>
+where
+	S: AsyncWrite + AsyncRead + Unpin,
+{
+	type Output = Result<(S, StreamProtocol), NegotiatorStreamError>;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let this = self.project();
+
+		if this.timeout.poll_unpin(cx).is_ready() {
+			return Poll::Ready(Err(NegotiatorStreamError::Timeout));
+		}
+
+		loop {
+			match std::mem::replace(this.state, InboundState::Done) {
+				InboundState::RecvHeader { mut io } => {
+					let msg = match Pin::new(&mut io).poll_next(cx) {
+						Poll::Pending => {
+							*this.state = InboundState::RecvHeader { io };
+							return Poll::Pending;
+						}
+						Poll::Ready(None) => return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed)),
+						Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(map_codec_error(e))),
+						Poll::Ready(Some(Ok(msg))) => msg,
+					};
+
+					if msg.as_ref() != HEADER {
+						return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed));
+					}
+
+					*this.state = InboundState::SendHeader { io };
+				}
+
+				InboundState::SendHeader { mut io } => {
+					match Pin::new(&mut io).poll_ready(cx) {
+						Poll::Pending => {
+							*this.state = InboundState::SendHeader { io };
+							return Poll::Pending;
+						}
+						Poll::Ready(Ok(())) => {}
+						Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+					}
+
+					if let Err(e) = Pin::new(&mut io).start_send(Bytes::from_static(HEADER)) {
+						return Poll::Ready(Err(NegotiatorStreamError::IoError(e)));
+					}
+
+					*this.state = InboundState::FlushHeader { io };
+				}
+
+				InboundState::FlushHeader { mut io } => match Pin::new(&mut io).poll_flush(cx) {
+					Poll::Pending => {
+						*this.state = InboundState::FlushHeader { io };
+						return Poll::Pending;
+					}
+					Poll::Ready(Ok(())) => *this.state = InboundState::RecvProposal { io },
+					Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+				},
+
+				InboundState::RecvProposal { mut io } => {
+					let msg = match Pin::new(&mut io).poll_next(cx) {
+						Poll::Pending => {
+							*this.state = InboundState::RecvProposal { io };
+							return Poll::Pending;
+						}
+						Poll::Ready(None) => return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed)),
+						Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(map_codec_error(e))),
+						Poll::Ready(Some(Ok(msg))) => msg,
+					};
+
+					let proposed = std::str::from_utf8(msg.as_ref())
+						.ok()
+						.and_then(|s| StreamProtocol::from_str(s).ok());
+					let selected = proposed.filter(|p| this.protocols.accepts(p));
+					tracing::debug!(?selected, "multistream-select 1.0.0 proposal received");
+
+					*this.state = InboundState::SendReply { io, selected };
+				}
+
+				InboundState::SendReply { mut io, selected } => {
+					match Pin::new(&mut io).poll_ready(cx) {
+						Poll::Pending => {
+							*this.state = InboundState::SendReply { io, selected };
+							return Poll::Pending;
+						}
+						Poll::Ready(Ok(())) => {}
+						Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+					}
+
+					let reply = match &selected {
+						Some(protocol) => Bytes::copy_from_slice(protocol.as_ref().as_bytes()),
+						None => Bytes::from_static(NOT_AVAILABLE),
+					};
+					if let Err(e) = Pin::new(&mut io).start_send(reply) {
+						return Poll::Ready(Err(NegotiatorStreamError::IoError(e)));
+					}
+
+					*this.state = InboundState::FlushReply { io, selected };
+				}
+
+				InboundState::FlushReply { mut io, selected } => match Pin::new(&mut io).poll_flush(cx) {
+					Poll::Pending => {
+						*this.state = InboundState::FlushReply { io, selected };
+						return Poll::Pending;
+					}
+					Poll::Ready(Ok(())) => match selected {
+						Some(protocol) => return Poll::Ready(Ok((io.into_inner(), protocol))),
+						None => *this.state = InboundState::RecvProposal { io },
+					},
+					Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+				},
+
+				InboundState::Done => panic!("Msv1InboundStream polled after completion"),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TEST_MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+	fn encode_frame(payload: &[u8]) -> BytesMut {
+		let mut buf = BytesMut::new();
+		let mut codec = Msv1Codec::new(TEST_MAX_MESSAGE_SIZE);
+		codec.encode(Bytes::copy_from_slice(payload), &mut buf).unwrap();
+		buf
+	}
+
+	#[test]
+	fn decode_round_trips_a_well_formed_frame() {
+		let mut codec = Msv1Codec::new(TEST_MAX_MESSAGE_SIZE);
+		let mut buf = encode_frame(b"/test/protocol@1.0.0");
+
+		let decoded = codec.decode(&mut buf).unwrap();
+		assert_eq!(decoded, Some(Bytes::from_static(b"/test/protocol@1.0.0")));
+	}
+
+	#[test]
+	fn decode_returns_none_on_incomplete_frame() {
+		let mut codec = Msv1Codec::new(TEST_MAX_MESSAGE_SIZE);
+		let mut full = encode_frame(b"/test/protocol@1.0.0");
+		let mut partial = full.split_to(full.len() - 1);
+
+		assert_eq!(codec.decode(&mut partial).unwrap(), None);
+	}
+
+	#[test]
+	fn decode_rejects_an_oversized_length_prefix() {
+		// A codec configured with a tiny limit, fed a varint length prefix declaring a frame far
+		// larger than that limit -- the remote shouldn't be able to force us to buffer an
+		// attacker-chosen amount of memory before we've even checked the length.
+		let mut codec = Msv1Codec::new(8);
+		let mut buf = BytesMut::new();
+		write_varint(&mut buf, 1024);
+		buf.extend_from_slice(&[0u8; 16]);
+
+		let error = codec.decode(&mut buf).unwrap_err();
+		assert_eq!(error.kind(), io::ErrorKind::FileTooLarge);
+	}
+
+	#[test]
+	fn decode_rejects_a_frame_missing_its_trailing_newline() {
+		// A well-formed length prefix whose payload was tampered with (or simply mis-encoded) so
+		// it doesn't end in the `\n` every real `Msv1Codec`-encoded frame carries.
+		let mut codec = Msv1Codec::new(TEST_MAX_MESSAGE_SIZE);
+		let mut buf = BytesMut::new();
+		write_varint(&mut buf, 4);
+		buf.extend_from_slice(b"abcd");
+
+		let error = codec.decode(&mut buf).unwrap_err();
+		assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+	}
+
+	#[test]
+	fn map_codec_error_translates_frame_too_large_kind() {
+		let io_error = io::Error::new(io::ErrorKind::FileTooLarge, "too big");
+		assert!(matches!(map_codec_error(io_error), NegotiatorStreamError::FrameTooLarge));
+	}
+
+	#[test]
+	fn map_codec_error_passes_other_kinds_through_as_io_error() {
+		let io_error = io::Error::new(io::ErrorKind::InvalidData, "bad frame");
+		assert!(matches!(map_codec_error(io_error), NegotiatorStreamError::IoError(_)));
+	}
+}
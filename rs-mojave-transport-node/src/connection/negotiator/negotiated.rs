@@ -0,0 +1,269 @@
+use std::{
+	io,
+	pin::Pin,
+	task::{Context, Poll},
+};
+
+use asynchronous_codec::{Decoder, LengthCodec};
+use bytes::{Buf, Bytes, BytesMut};
+use futures::{AsyncRead, AsyncWrite};
+
+use crate::{
+	StreamProtocol,
+	connection::negotiator::{NegotiatorStreamError, multistream_select_v1::Msv1Codec},
+};
+
+/// The stream handed back once negotiation completes.
+///
+/// [`super::negotiate`] always resolves [`Negotiated::Completed`], except when the dialer used
+/// [`super::NegotiationTiming::Lazy`] on a single-protocol proposal (see [`super::OutboundStream`]):
+/// in that case it resolves [`Negotiated::ExpectingJson`] or [`Negotiated::ExpectingMsv1`] as soon as the proposal has been flushed,
+/// without waiting for the listener's reply. The reply is then verified transparently on the
+/// first read, surfacing a mismatch as [`NegotiatorStreamError::UnsupportedProtocol`] instead of
+/// the requested application data.
+pub enum Negotiated<S> {
+	Completed(S),
+	ExpectingJson(ExpectingVerify<S, LengthCodec>),
+	ExpectingMsv1(ExpectingVerify<S, Msv1Codec>),
+}
+
+impl<S> Negotiated<S>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	pub(crate) fn expecting_json(stream: S, expected: StreamProtocol, max_message_size: usize) -> Self {
+		Negotiated::ExpectingJson(ExpectingVerify::new(stream, LengthCodec, expected, verify_json, max_message_size))
+	}
+
+	pub(crate) fn expecting_msv1(stream: S, expected: StreamProtocol, max_message_size: usize) -> Self {
+		Negotiated::ExpectingMsv1(ExpectingVerify::new(stream, Msv1Codec::new(max_message_size), expected, verify_msv1, max_message_size))
+	}
+}
+
+fn verify_json(msg: &Bytes, expected: &StreamProtocol) -> Result<(), NegotiatorStreamError> {
+	let selected: Option<StreamProtocol> = serde_json::from_slice(msg.as_ref()).map_err(|_| NegotiatorStreamError::InvalidProtocol)?;
+	match selected {
+		Some(protocol) if protocol == *expected => Ok(()),
+		_ => Err(NegotiatorStreamError::UnsupportedProtocol),
+	}
+}
+
+fn verify_msv1(msg: &Bytes, expected: &StreamProtocol) -> Result<(), NegotiatorStreamError> {
+	if msg.as_ref() == expected.as_ref().as_bytes() {
+		Ok(())
+	} else {
+		Err(NegotiatorStreamError::UnsupportedProtocol)
+	}
+}
+
+/// A stream whose first bytes are still the remote's as-yet-unread reply to an optimistically
+/// proposed protocol. `verify` decodes that reply with `C` and checks it matches `expected`;
+/// once verified, any bytes `C::decode` left unconsumed in `buf` are real application data the
+/// remote pipelined right behind its reply, and are drained before falling through to raw reads.
+pub(crate) struct ExpectingVerify<S, C> {
+	stream: S,
+	codec: C,
+	expected: StreamProtocol,
+	max_message_size: usize,
+	buf: BytesMut,
+	verified: bool,
+	verify: fn(&Bytes, &StreamProtocol) -> Result<(), NegotiatorStreamError>,
+}
+
+impl<S, C> ExpectingVerify<S, C>
+where
+	S: AsyncRead + Unpin,
+	C: Decoder<Item = Bytes, Error = io::Error> + Unpin,
+{
+	fn new(
+		stream: S,
+		codec: C,
+		expected: StreamProtocol,
+		verify: fn(&Bytes, &StreamProtocol) -> Result<(), NegotiatorStreamError>,
+		max_message_size: usize,
+	) -> Self {
+		Self {
+			stream,
+			codec,
+			expected,
+			max_message_size,
+			buf: BytesMut::new(),
+			verified: false,
+			verify,
+		}
+	}
+
+	fn poll_verify(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		loop {
+			match self.codec.decode(&mut self.buf)? {
+				Some(msg) => {
+					if msg.len() > self.max_message_size {
+						return Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, NegotiatorStreamError::FrameTooLarge)));
+					}
+					(self.verify)(&msg, &self.expected).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+					self.verified = true;
+					return Poll::Ready(Ok(()));
+				}
+				None => {
+					let mut scratch = [0u8; 1024];
+					match Pin::new(&mut self.stream).poll_read(cx, &mut scratch) {
+						Poll::Pending => return Poll::Pending,
+						Poll::Ready(Ok(0)) => {
+							return Poll::Ready(Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed during optimistic negotiation")));
+						}
+						Poll::Ready(Ok(n)) => self.buf.extend_from_slice(&scratch[..n]),
+						Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+					}
+				}
+			}
+		}
+	}
+}
+
+impl<S, C> AsyncRead for ExpectingVerify<S, C>
+where
+	S: AsyncRead + Unpin,
+	C: Decoder<Item = Bytes, Error = io::Error> + Unpin,
+{
+	fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+		let this = self.get_mut();
+
+		if !this.verified {
+			match this.poll_verify(cx) {
+				Poll::Pending => return Poll::Pending,
+				Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+				Poll::Ready(Ok(())) => {}
+			}
+		}
+
+		if !this.buf.is_empty() {
+			let n = buf.len().min(this.buf.len());
+			buf[..n].copy_from_slice(&this.buf[..n]);
+			this.buf.advance(n);
+			return Poll::Ready(Ok(n));
+		}
+
+		Pin::new(&mut this.stream).poll_read(cx, buf)
+	}
+}
+
+impl<S, C> AsyncWrite for ExpectingVerify<S, C>
+where
+	S: AsyncWrite + Unpin,
+	C: Unpin,
+{
+	fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+		Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+	}
+
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.get_mut().stream).poll_close(cx)
+	}
+}
+
+impl<S> AsyncRead for Negotiated<S>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+		match self.get_mut() {
+			Negotiated::Completed(s) => Pin::new(s).poll_read(cx, buf),
+			Negotiated::ExpectingJson(e) => Pin::new(e).poll_read(cx, buf),
+			Negotiated::ExpectingMsv1(e) => Pin::new(e).poll_read(cx, buf),
+		}
+	}
+}
+
+impl<S> AsyncWrite for Negotiated<S>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+		match self.get_mut() {
+			Negotiated::Completed(s) => Pin::new(s).poll_write(cx, buf),
+			Negotiated::ExpectingJson(e) => Pin::new(e).poll_write(cx, buf),
+			Negotiated::ExpectingMsv1(e) => Pin::new(e).poll_write(cx, buf),
+		}
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		match self.get_mut() {
+			Negotiated::Completed(s) => Pin::new(s).poll_flush(cx),
+			Negotiated::ExpectingJson(e) => Pin::new(e).poll_flush(cx),
+			Negotiated::ExpectingMsv1(e) => Pin::new(e).poll_flush(cx),
+		}
+	}
+
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		match self.get_mut() {
+			Negotiated::Completed(s) => Pin::new(s).poll_close(cx),
+			Negotiated::ExpectingJson(e) => Pin::new(e).poll_close(cx),
+			Negotiated::ExpectingMsv1(e) => Pin::new(e).poll_close(cx),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn protocol(version: &str) -> StreamProtocol {
+		StreamProtocol::new("test", "protocol", version.parse().unwrap())
+	}
+
+	#[test]
+	fn verify_json_rejects_malformed_json() {
+		let expected = protocol("1.0.0");
+		let error = verify_json(&Bytes::from_static(b"not json"), &expected).unwrap_err();
+
+		assert!(matches!(error, NegotiatorStreamError::InvalidProtocol));
+	}
+
+	#[test]
+	fn verify_json_accepts_the_expected_protocol() {
+		let expected = protocol("1.0.0");
+		let msg = serde_json::to_vec(&Some(&expected)).unwrap();
+
+		assert!(verify_json(&Bytes::from(msg), &expected).is_ok());
+	}
+
+	#[test]
+	fn verify_json_rejects_a_different_protocol() {
+		let expected = protocol("1.0.0");
+		let other = protocol("2.0.0");
+		let msg = serde_json::to_vec(&Some(&other)).unwrap();
+
+		let error = verify_json(&Bytes::from(msg), &expected).unwrap_err();
+		assert!(matches!(error, NegotiatorStreamError::UnsupportedProtocol));
+	}
+
+	#[test]
+	fn verify_json_rejects_a_null_reply() {
+		let expected = protocol("1.0.0");
+		let msg = serde_json::to_vec(&Option::<StreamProtocol>::None).unwrap();
+
+		let error = verify_json(&Bytes::from(msg), &expected).unwrap_err();
+		assert!(matches!(error, NegotiatorStreamError::UnsupportedProtocol));
+	}
+
+	#[test]
+	fn verify_msv1_accepts_the_expected_protocol() {
+		let expected = protocol("1.0.0");
+		let msg = Bytes::copy_from_slice(expected.as_ref().as_bytes());
+
+		assert!(verify_msv1(&msg, &expected).is_ok());
+	}
+
+	#[test]
+	fn verify_msv1_rejects_a_mismatched_reply() {
+		let expected = protocol("1.0.0");
+		let msg = Bytes::from_static(b"/garbage/reply");
+
+		let error = verify_msv1(&msg, &expected).unwrap_err();
+		assert!(matches!(error, NegotiatorStreamError::UnsupportedProtocol));
+	}
+}
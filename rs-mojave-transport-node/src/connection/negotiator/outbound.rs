@@ -12,12 +12,65 @@ use pin_project::pin_project;
 
 use crate::{
 	StreamProtocol,
-	connection::negotiator::{NegotiatorStreamError, StreamProtocols},
+	connection::negotiator::{
+		NegotiationConfig, NegotiationFormat, NegotiationTiming, Negotiated, NegotiatorStreamError, StreamProtocols,
+		multistream_select_v1::Msv1OutboundStream,
+	},
 };
 
-#[pin_project]
+/// Drives the dialer side of multistream-select negotiation, in whichever [`NegotiationFormat`]
+/// and [`NegotiationTiming`] were requested.
+#[pin_project(project = OutboundStreamProj)]
 pub struct OutboundStream<S> {
+	#[pin]
+	inner: Inner<S>,
+}
+
+#[pin_project(project = InnerProj)]
+enum Inner<S> {
+	Json(#[pin] JsonOutboundStream<S>),
+	MultistreamSelectV1(#[pin] Msv1OutboundStream<S>),
+}
+
+impl<S> OutboundStream<S>
+where
+	S: AsyncWrite + AsyncRead + Unpin,
+{
+	pub(crate) fn new(config: NegotiationConfig, protocols: StreamProtocols, stream: S) -> Self {
+		let inner = match config.format {
+			NegotiationFormat::Json => Inner::Json(JsonOutboundStream::new(config, protocols, stream)),
+			NegotiationFormat::MultistreamSelectV1 => Inner::MultistreamSelectV1(Msv1OutboundStream::new(config, protocols, stream)),
+		};
+
+		OutboundStream { inner }
+	}
+}
+
+impl<S> Future for OutboundStream<S>
+where
+	S: AsyncWrite + AsyncRead + Unpin,
+{
+	type Output = Result<(Negotiated<S>, StreamProtocol), NegotiatorStreamError>;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		match self.project().inner.project() {
+			InnerProj::Json(s) => s.poll(cx),
+			InnerProj::MultistreamSelectV1(s) => s.poll(cx),
+		}
+	}
+}
+
+/// Proposes the full [`StreamProtocols`] priority list as a single length-prefixed JSON message.
+/// Under [`NegotiationTiming::Eager`] (or when more than one protocol is proposed), waits for the
+/// listener's reply -- either the single agreed [`StreamProtocol`] or `null` if none of the
+/// proposed protocols were supported. Under [`NegotiationTiming::Lazy`] with exactly one proposed
+/// protocol, resolves as soon as the proposal is flushed and defers checking the reply to the
+/// first read of the returned [`Negotiated::ExpectingJson`] stream.
+#[pin_project]
+struct JsonOutboundStream<S> {
 	timeout: Delay,
+	timing: NegotiationTiming,
+	max_message_size: usize,
 	protocols: StreamProtocols,
 	state: OutboundState<S>,
 }
@@ -31,31 +84,32 @@ enum OutboundState<S> {
 	},
 	RecvProtocol {
 		io: Framed<S, LengthCodec>,
-		received_protocols: Option<Vec<StreamProtocol>>,
 	},
 	Done,
 }
 
-impl<S> OutboundStream<S>
+impl<S> JsonOutboundStream<S>
 where
 	S: AsyncWrite + AsyncRead + Unpin,
 {
-	pub(crate) fn new(protocols: StreamProtocols, stream: S) -> Self {
+	fn new(config: NegotiationConfig, protocols: StreamProtocols, stream: S) -> Self {
 		let framed = Framed::new(stream, LengthCodec);
 
-		OutboundStream {
+		JsonOutboundStream {
 			protocols,
+			timing: config.timing,
+			max_message_size: config.max_message_size,
 			timeout: Delay::new(Duration::from_secs(15)),
 			state: OutboundState::SendProtocol { io: framed },
 		}
 	}
 }
 
-impl<S> Future for OutboundStream<S>
+impl<S> Future for JsonOutboundStream<S>
 where
 	S: AsyncWrite + AsyncRead + Unpin,
 {
-	type Output = Result<S, NegotiatorStreamError>;
+	type Output = Result<(Negotiated<S>, StreamProtocol), NegotiatorStreamError>;
 
 	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
 		let this = self.project();
@@ -76,7 +130,10 @@ where
 						Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
 					}
 
-					let protocols_json = serde_json::to_vec(&this.protocols).unwrap();
+					let protocols_json = match serde_json::to_vec(&this.protocols) {
+						Ok(json) => json,
+						Err(_) => return Poll::Ready(Err(NegotiatorStreamError::InvalidProtocol)),
+					};
 					if let Err(err) = Pin::new(&mut io).start_send(protocols_json.into()) {
 						return Poll::Ready(Err(NegotiatorStreamError::IoError(err)));
 					}
@@ -86,10 +143,14 @@ where
 
 				OutboundState::Flush { mut io } => match Pin::new(&mut io).poll_flush(cx)? {
 					Poll::Ready(()) => {
-						*this.state = OutboundState::RecvProtocol {
-							io,
-							received_protocols: None,
+						if *this.timing == NegotiationTiming::Lazy && this.protocols.0.len() == 1 {
+							let expected = this.protocols.0[0].clone();
+							return Poll::Ready(Ok((
+								Negotiated::expecting_json(io.into_inner(), expected.clone(), *this.max_message_size),
+								expected,
+							)));
 						}
+						*this.state = OutboundState::RecvProtocol { io };
 					}
 					Poll::Pending => {
 						*this.state = OutboundState::Flush { io };
@@ -97,13 +158,10 @@ where
 					}
 				},
 
-				OutboundState::RecvProtocol {
-					mut io,
-					received_protocols,
-				} => {
+				OutboundState::RecvProtocol { mut io } => {
 					let msg: Bytes = match Pin::new(&mut io).poll_next(cx) {
 						Poll::Pending => {
-							*this.state = OutboundState::RecvProtocol { io, received_protocols };
+							*this.state = OutboundState::RecvProtocol { io };
 							return Poll::Pending;
 						}
 						Poll::Ready(None) => return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed)),
@@ -111,10 +169,20 @@ where
 						Poll::Ready(Some(Ok(msg))) => msg,
 					};
 
-					let received_protocols: Vec<StreamProtocol> = serde_json::from_slice(msg.as_ref()).unwrap();
-					tracing::info!("Received protocols: {:?}", received_protocols);
+					if msg.len() > *this.max_message_size {
+						return Poll::Ready(Err(NegotiatorStreamError::FrameTooLarge));
+					}
+
+					let selected: Option<StreamProtocol> = match serde_json::from_slice(msg.as_ref()) {
+						Ok(selected) => selected,
+						Err(_) => return Poll::Ready(Err(NegotiatorStreamError::InvalidProtocol)),
+					};
+					tracing::debug!(?selected, "received negotiation reply");
 
-					return Poll::Ready(Ok(io.into_inner()));
+					return Poll::Ready(match selected {
+						Some(protocol) => Ok((Negotiated::Completed(io.into_inner()), protocol)),
+						None => Err(NegotiatorStreamError::UnsupportedProtocol),
+					});
 				}
 
 				OutboundState::Done => panic!("State::poll called after completion"),
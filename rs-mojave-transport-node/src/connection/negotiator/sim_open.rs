@@ -0,0 +1,299 @@
+use std::{
+	cmp::Ordering,
+	pin::Pin,
+	task::{Context, Poll},
+	time::Duration,
+};
+
+use asynchronous_codec::{Framed, LengthCodec};
+use bytes::Bytes;
+use futures::{AsyncRead, AsyncWrite, FutureExt, Sink, Stream};
+use futures_timer::Delay;
+use pin_project::pin_project;
+use rand::{RngCore, rng};
+
+use crate::connection::negotiator::NegotiatorStreamError;
+
+/// The reserved multistream-select protocol token proposed by a dialer that supports
+/// simultaneous-open role arbitration (see [`SimOpenStream`]).
+pub const SIM_OPEN_PROTOCOL: &str = "/libp2p/simultaneous-connect";
+
+const NONCE_LEN: usize = 32;
+
+/// Number of times both sides are allowed to re-roll their nonce after an exact tie before
+/// giving up on arbitration.
+const MAX_TIE_RETRIES: u32 = 5;
+
+/// The outcome of running [`SimOpenStream`] to completion.
+pub enum SimOpenOutcome<S> {
+	/// The remote didn't echo [`SIM_OPEN_PROTOCOL`]: negotiation should fall back to the plain
+	/// multistream-select path on `stream`.
+	Fallback(S),
+	/// This side's nonce won the comparison: it should act as the dialer for the ensuing
+	/// multistream-select negotiation.
+	Initiator(S),
+	/// This side's nonce lost the comparison: it should act as the listener for the ensuing
+	/// multistream-select negotiation.
+	Responder(S),
+}
+
+/// Negotiates which side of a simultaneously-opened connection (e.g. two peers dialing each
+/// other at once while hole punching) acts as the initiator.
+///
+/// Both sides propose [`SIM_OPEN_PROTOCOL`]; if the remote doesn't echo it back, arbitration
+/// falls back to plain negotiation. Otherwise each side generates a random nonce and the side
+/// with the numerically larger nonce becomes the initiator. An exact tie is resolved by both
+/// sides discarding their nonce and retrying, bounded by [`MAX_TIE_RETRIES`].
+#[pin_project]
+pub struct SimOpenStream<S> {
+	timeout: Delay,
+	retries_left: u32,
+	state: State<S>,
+}
+
+enum State<S> {
+	SendToken { io: Framed<S, LengthCodec> },
+	FlushToken { io: Framed<S, LengthCodec> },
+	RecvToken { io: Framed<S, LengthCodec> },
+	SendNonce { io: Framed<S, LengthCodec>, nonce: [u8; NONCE_LEN] },
+	FlushNonce { io: Framed<S, LengthCodec>, nonce: [u8; NONCE_LEN] },
+	RecvNonce { io: Framed<S, LengthCodec>, nonce: [u8; NONCE_LEN] },
+	SendRole { io: Framed<S, LengthCodec>, initiator: bool },
+	FlushRole { io: Framed<S, LengthCodec>, initiator: bool },
+	RecvRole { io: Framed<S, LengthCodec>, initiator: bool },
+	Done,
+}
+
+impl<S> SimOpenStream<S>
+where
+	S: AsyncWrite + AsyncRead + Unpin,
+{
+	pub(crate) fn new(stream: S) -> Self {
+		Self {
+			timeout: Delay::new(Duration::from_secs(15)),
+			retries_left: MAX_TIE_RETRIES,
+			state: State::SendToken { io: Framed::new(stream, LengthCodec) },
+		}
+	}
+}
+
+fn random_nonce() -> [u8; NONCE_LEN] {
+	let mut nonce = [0u8; NONCE_LEN];
+	rng().fill_bytes(&mut nonce);
+	nonce
+}
+
+fn encode_nonce(nonce: &[u8; NONCE_LEN]) -> Bytes {
+	let mut encoded = b"select:".to_vec();
+	for byte in nonce {
+		encoded.push(HEX_DIGITS[(byte >> 4) as usize]);
+		encoded.push(HEX_DIGITS[(byte & 0xf) as usize]);
+	}
+	Bytes::from(encoded)
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+fn decode_nonce(msg: &[u8]) -> Option<[u8; NONCE_LEN]> {
+	let hex = msg.strip_prefix(b"select:")?;
+	if hex.len() != NONCE_LEN * 2 {
+		return None;
+	}
+
+	let mut nonce = [0u8; NONCE_LEN];
+	for (i, byte) in nonce.iter_mut().enumerate() {
+		let hi = (hex[i * 2] as char).to_digit(16)?;
+		let lo = (hex[i * 2 + 1] as char).to_digit(16)?;
+		*byte = ((hi << 4) | lo) as u8;
+	}
+	Some(nonce)
+}
+
+impl<S> Future for SimOpenStream<S>
+where
+	S: AsyncWrite + AsyncRead + Unpin,
+{
+	type Output = Result<SimOpenOutcome<S>, NegotiatorStreamError>;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let this = self.project();
+
+		if this.timeout.poll_unpin(cx).is_ready() {
+			return Poll::Ready(Err(NegotiatorStreamError::Timeout));
+		}
+
+		loop {
+			match std::mem::replace(this.state, State::Done) {
+				State::SendToken { mut io } => {
+					match Pin::new(&mut io).poll_ready(cx) {
+						Poll::Pending => {
+							*this.state = State::SendToken { io };
+							return Poll::Pending;
+						}
+						Poll::Ready(Ok(())) => {}
+						Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+					}
+
+					if let Err(e) = Pin::new(&mut io).start_send(Bytes::from_static(SIM_OPEN_PROTOCOL.as_bytes())) {
+						return Poll::Ready(Err(NegotiatorStreamError::IoError(e)));
+					}
+
+					*this.state = State::FlushToken { io };
+				}
+
+				State::FlushToken { mut io } => match Pin::new(&mut io).poll_flush(cx) {
+					Poll::Pending => {
+						*this.state = State::FlushToken { io };
+						return Poll::Pending;
+					}
+					Poll::Ready(Ok(())) => {
+						*this.state = State::RecvToken { io };
+					}
+					Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+				},
+
+				State::RecvToken { mut io } => {
+					let msg: Bytes = match Pin::new(&mut io).poll_next(cx) {
+						Poll::Pending => {
+							*this.state = State::RecvToken { io };
+							return Poll::Pending;
+						}
+						Poll::Ready(None) => return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed)),
+						Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+						Poll::Ready(Some(Ok(msg))) => msg,
+					};
+
+					if msg.as_ref() != SIM_OPEN_PROTOCOL.as_bytes() {
+						tracing::debug!("peer doesn't support simultaneous-open, falling back");
+						return Poll::Ready(Ok(SimOpenOutcome::Fallback(io.into_inner())));
+					}
+
+					*this.state = State::SendNonce { io, nonce: random_nonce() };
+				}
+
+				State::SendNonce { mut io, nonce } => {
+					match Pin::new(&mut io).poll_ready(cx) {
+						Poll::Pending => {
+							*this.state = State::SendNonce { io, nonce };
+							return Poll::Pending;
+						}
+						Poll::Ready(Ok(())) => {}
+						Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+					}
+
+					if let Err(e) = Pin::new(&mut io).start_send(encode_nonce(&nonce)) {
+						return Poll::Ready(Err(NegotiatorStreamError::IoError(e)));
+					}
+
+					*this.state = State::FlushNonce { io, nonce };
+				}
+
+				State::FlushNonce { mut io, nonce } => match Pin::new(&mut io).poll_flush(cx) {
+					Poll::Pending => {
+						*this.state = State::FlushNonce { io, nonce };
+						return Poll::Pending;
+					}
+					Poll::Ready(Ok(())) => {
+						*this.state = State::RecvNonce { io, nonce };
+					}
+					Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+				},
+
+				State::RecvNonce { mut io, nonce } => {
+					let msg: Bytes = match Pin::new(&mut io).poll_next(cx) {
+						Poll::Pending => {
+							*this.state = State::RecvNonce { io, nonce };
+							return Poll::Pending;
+						}
+						Poll::Ready(None) => return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed)),
+						Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+						Poll::Ready(Some(Ok(msg))) => msg,
+					};
+
+					let Some(peer_nonce) = decode_nonce(msg.as_ref()) else {
+						return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed));
+					};
+
+					match peer_nonce.cmp(&nonce) {
+						Ordering::Greater => *this.state = State::SendRole { io, initiator: false },
+						Ordering::Less => *this.state = State::SendRole { io, initiator: true },
+						Ordering::Equal => {
+							if *this.retries_left == 0 {
+								return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed));
+							}
+							*this.retries_left -= 1;
+							*this.state = State::SendNonce { io, nonce: random_nonce() };
+						}
+					}
+				}
+
+				State::SendRole { mut io, initiator } => {
+					match Pin::new(&mut io).poll_ready(cx) {
+						Poll::Pending => {
+							*this.state = State::SendRole { io, initiator };
+							return Poll::Pending;
+						}
+						Poll::Ready(Ok(())) => {}
+						Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+					}
+
+					let role = if initiator { "initiator" } else { "responder" };
+					if let Err(e) = Pin::new(&mut io).start_send(Bytes::from_static(role.as_bytes())) {
+						return Poll::Ready(Err(NegotiatorStreamError::IoError(e)));
+					}
+
+					*this.state = State::FlushRole { io, initiator };
+				}
+
+				State::FlushRole { mut io, initiator } => match Pin::new(&mut io).poll_flush(cx) {
+					Poll::Pending => {
+						*this.state = State::FlushRole { io, initiator };
+						return Poll::Pending;
+					}
+					Poll::Ready(Ok(())) => {
+						*this.state = State::RecvRole { io, initiator };
+					}
+					Poll::Ready(Err(e)) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+				},
+
+				// The peer sent its own role frame right after ours; read it off the stream now
+				// so it isn't left sitting in front of the multistream-select proposal that
+				// follows -- otherwise the listener side would decode these stray bytes as the
+				// first protocol proposal and fail with `InvalidProtocol`/`NegotiationFailed`.
+				State::RecvRole { mut io, initiator } => {
+					let msg: Bytes = match Pin::new(&mut io).poll_next(cx) {
+						Poll::Pending => {
+							*this.state = State::RecvRole { io, initiator };
+							return Poll::Pending;
+						}
+						Poll::Ready(None) => return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed)),
+						Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(NegotiatorStreamError::IoError(e))),
+						Poll::Ready(Some(Ok(msg))) => msg,
+					};
+
+					let peer_initiator = match msg.as_ref() {
+						b"initiator" => true,
+						b"responder" => false,
+						_ => return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed)),
+					};
+
+					// Both sides compare the same two nonces, so their conclusions should always
+					// be opposite; agreeing roles means the nonce comparison desynced somehow and
+					// it's not safe to proceed.
+					if peer_initiator == initiator {
+						return Poll::Ready(Err(NegotiatorStreamError::NegotiationFailed));
+					}
+
+					let stream = io.into_inner();
+					return Poll::Ready(Ok(if initiator {
+						SimOpenOutcome::Initiator(stream)
+					} else {
+						SimOpenOutcome::Responder(stream)
+					}));
+				}
+
+				State::Done => panic!("SimOpenStream is in a done state"),
+			}
+		}
+	}
+}
@@ -1,6 +1,8 @@
 use multiaddr::Multiaddr;
 use rs_mojave_network_core::transport::{Protocol, TransportError};
 
+use crate::peer::manager::ConnectionLimit;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
 	#[error("no protocols in multiaddr: {0}")]
@@ -14,6 +16,20 @@ pub enum Error {
 
 	#[error("dial to: {0:?} error: {1}")]
 	DialError(Multiaddr, TransportError<std::io::Error>),
+
+	/// A dial or incoming connection would have exceeded a configured
+	/// [`crate::peer::manager::ConnectionLimits`].
+	#[error("connection limit exceeded: {kind:?} (current {current}, limit {limit})")]
+	ConnectionLimit { kind: ConnectionLimit, current: u32, limit: u32 },
+
+	/// [`crate::Node::dial`] refused the dial synchronously because it would exceed the pending
+	/// outgoing connection limit, as opposed to a [`crate::peer::PendingOutboundConnectionError`]
+	/// discovered asynchronously after the dial was already handed off. Other synchronous
+	/// refusals are not wrapped in this variant: a multiaddr with no known protocol or a missing
+	/// transport is returned directly as [`Error::NoProtocolsInMultiaddr`] or
+	/// [`Error::TransportNotFound`].
+	#[error("dial refused immediately: {0}")]
+	ImmediateDial(#[source] Box<Error>),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -1,17 +1,59 @@
 use multiaddr::{Multiaddr, PeerId};
+use std::fmt;
 use std::io;
+use std::num::NonZeroU32;
 
+use crate::ConnectionError;
 use crate::connection::ConnectionId;
+use crate::peer::manager::ConnectionLimit;
+use crate::protocol::{PeerProtocol, THandlerToEvent};
 
-#[derive(Debug)]
-pub enum NodeEvent {
+/// Everything a running [`crate::Node`] reports back to its caller through its [`futures::Stream`]
+/// implementation: connection/listener lifecycle events, plus whatever the node's [`PeerProtocol`]
+/// and its per-connection [`crate::protocol::ProtocolHandler`]s raise.
+pub enum NodeEvent<TProtocols>
+where
+	TProtocols: PeerProtocol,
+{
 	ConnectionEstablished {
 		connection_id: ConnectionId,
 		peer_id: PeerId,
 	},
+	/// An established connection was closed, either by the remote, during shutdown, or because of
+	/// a connection-level error.
+	ConnectionClosed {
+		connection_id: ConnectionId,
+		peer_id: PeerId,
+		error: Option<ConnectionError>,
+		/// How many other connections to the same peer are still established. `None` means this
+		/// was the last one.
+		remaining_for_peer: Option<NonZeroU32>,
+	},
 	IncomingConnection {
 		remote_address: Multiaddr,
 	},
+	/// A dial or incoming connection was refused because it would have exceeded a configured
+	/// [`crate::peer::manager::ConnectionLimits`].
+	ConnectionDenied {
+		connection_id: ConnectionId,
+		limit: ConnectionLimit,
+	},
+	/// The observed remote address of an established connection changed.
+	ConnectionAddressChanged {
+		connection_id: ConnectionId,
+		peer_id: PeerId,
+		new_address: Multiaddr,
+	},
+	/// A per-connection [`crate::protocol::ProtocolHandler`] raised an event -- the `Node`-level
+	/// counterpart of [`crate::peer::manager::PeerEvent::Notification`].
+	Notification {
+		connection_id: ConnectionId,
+		peer_id: PeerId,
+		event: THandlerToEvent<TProtocols>,
+	},
+	/// An application-level event raised by the node's [`PeerProtocol`] itself, via
+	/// [`PeerProtocol::poll`] returning [`crate::protocol::Action::Event`].
+	Protocol(TProtocols::ToNode),
 	NewListenAddr {
 		address: Multiaddr,
 	},
@@ -24,4 +66,63 @@ pub enum NodeEvent {
 	ListenerError {
 		error: io::Error,
 	},
+	/// The shutdown begun by [`crate::Node::shutdown`] has finished: every pending and
+	/// established connection has gone away. The last event this `Node`'s stream will ever yield.
+	ShutdownComplete,
+}
+
+// Derived `Debug` would add a spurious `TProtocols: Debug` bound on top of the ones the trait's
+// associated types already carry, so this is implemented by hand (mirroring how the protocol
+// crates hand-roll `Debug` for their own generic `Event<C>` types).
+impl<TProtocols> fmt::Debug for NodeEvent<TProtocols>
+where
+	TProtocols: PeerProtocol,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			NodeEvent::ConnectionEstablished { connection_id, peer_id } => {
+				f.debug_struct("ConnectionEstablished").field("connection_id", connection_id).field("peer_id", peer_id).finish()
+			}
+			NodeEvent::ConnectionClosed {
+				connection_id,
+				peer_id,
+				error,
+				remaining_for_peer,
+			} => f
+				.debug_struct("ConnectionClosed")
+				.field("connection_id", connection_id)
+				.field("peer_id", peer_id)
+				.field("error", error)
+				.field("remaining_for_peer", remaining_for_peer)
+				.finish(),
+			NodeEvent::IncomingConnection { remote_address } => {
+				f.debug_struct("IncomingConnection").field("remote_address", remote_address).finish()
+			}
+			NodeEvent::ConnectionDenied { connection_id, limit } => {
+				f.debug_struct("ConnectionDenied").field("connection_id", connection_id).field("limit", limit).finish()
+			}
+			NodeEvent::ConnectionAddressChanged {
+				connection_id,
+				peer_id,
+				new_address,
+			} => f
+				.debug_struct("ConnectionAddressChanged")
+				.field("connection_id", connection_id)
+				.field("peer_id", peer_id)
+				.field("new_address", new_address)
+				.finish(),
+			NodeEvent::Notification { connection_id, peer_id, event } => f
+				.debug_struct("Notification")
+				.field("connection_id", connection_id)
+				.field("peer_id", peer_id)
+				.field("event", event)
+				.finish(),
+			NodeEvent::Protocol(event) => f.debug_tuple("Protocol").field(event).finish(),
+			NodeEvent::NewListenAddr { address } => f.debug_struct("NewListenAddr").field("address", address).finish(),
+			NodeEvent::AddressExpired { address } => f.debug_struct("AddressExpired").field("address", address).finish(),
+			NodeEvent::ListenerClosed { reason } => f.debug_struct("ListenerClosed").field("reason", reason).finish(),
+			NodeEvent::ListenerError { error } => f.debug_struct("ListenerError").field("error", error).finish(),
+			NodeEvent::ShutdownComplete => f.debug_struct("ShutdownComplete").finish(),
+		}
+	}
 }
@@ -0,0 +1,51 @@
+use std::{future::Future, pin::Pin};
+
+/// Runs a connection-level future (a handshake upgrade, a muxer drive loop) to completion,
+/// independently of whoever is polling [`crate::Node`].
+///
+/// [`peer::manager::Manager::with_executor`](crate::peer::manager::Manager::with_executor) lets
+/// callers swap in a runtime-specific implementation (tokio, async-std, a wasm-bindgen local
+/// spawner, ...) instead of relying on [`get_executor`]'s default.
+pub trait Executor {
+	fn exec(&self, future: Pin<Box<dyn Future<Output = ()> + Send>>);
+}
+
+/// Runs spawned futures to completion on a dedicated OS thread, via
+/// [`futures::executor::block_on`]. This is [`get_executor`]'s default when no runtime-specific
+/// executor is configured, and preserves a node's ability to run without depending on any
+/// particular async runtime being present.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InlineExecutor;
+
+impl Executor for InlineExecutor {
+	fn exec(&self, future: Pin<Box<dyn Future<Output = ()> + Send>>) {
+		std::thread::spawn(move || futures::executor::block_on(future));
+	}
+}
+
+/// Runs spawned futures on the ambient [`tokio`] runtime via [`tokio::spawn`]. Requires a tokio
+/// runtime to already be running on the calling thread.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokioExecutor;
+
+#[cfg(feature = "tokio")]
+impl Executor for TokioExecutor {
+	fn exec(&self, future: Pin<Box<dyn Future<Output = ()> + Send>>) {
+		tokio::spawn(future);
+	}
+}
+
+/// Returns the process-default [`Executor`]: [`TokioExecutor`] when the `tokio` feature is
+/// enabled, otherwise [`InlineExecutor`].
+#[cfg(feature = "tokio")]
+pub fn get_executor() -> TokioExecutor {
+	TokioExecutor
+}
+
+/// Returns the process-default [`Executor`]: [`TokioExecutor`] when the `tokio` feature is
+/// enabled, otherwise [`InlineExecutor`].
+#[cfg(not(feature = "tokio"))]
+pub fn get_executor() -> InlineExecutor {
+	InlineExecutor
+}
@@ -3,6 +3,7 @@ mod connection;
 mod error;
 mod event;
 mod executor;
+mod metrics;
 mod node;
 mod peer;
 mod protocol;
@@ -10,4 +11,7 @@ mod stream_protocol;
 
 pub use builder::Builder;
 pub use event::NodeEvent;
+pub use executor::Executor;
+pub use metrics::Metrics;
 pub use node::Node;
+pub use peer::manager::{ConnectionLimit, ConnectionLimits};
@@ -13,40 +13,77 @@ use tracing::{error, info};
 
 use crate::connection::ConnectionId;
 use crate::error::Error;
-use crate::peer::manager::{self, PeerEvent};
+use crate::metrics::Metrics;
+use crate::peer::manager::{self, ConnectionLimits, PeerEvent};
+use crate::protocol::{Action, PeerProtocol, THandlerFromEvent};
 use crate::{NodeEvent, peer};
 
 type TransportEventBoxed =
 	TransportEvent<<transport::Boxed<(PeerId, StreamMuxerBox)> as Transport>::ListenerUpgrade, io::Error>;
 
-pub struct Node<TProtocols> {
+pub struct Node<TProtocols>
+where
+	TProtocols: crate::protocol::PeerProtocol,
+{
 	pub peer_id: PeerId,
 	transports: HashMap<Protocol, Boxed<(PeerId, StreamMuxerBox)>>,
-	peer_manager: peer::manager::Manager,
-	pending_events: VecDeque<NodeEvent>,
+	peer_manager: peer::manager::Manager<crate::protocol::THandler<TProtocols>>,
+	pending_events: VecDeque<NodeEvent<TProtocols>>,
+	metrics: Option<Metrics>,
+
+	/// Set by [`Node::shutdown`]. While set, [`Node::poll_next_event`] drives
+	/// [`peer::manager::Manager::poll_close`] instead of [`peer::manager::Manager::poll`].
+	shutting_down: bool,
 
 	protocols: TProtocols,
 }
 
-impl<TProtocols> Unpin for Node<TProtocols> {}
+impl<TProtocols> Unpin for Node<TProtocols> where TProtocols: crate::protocol::PeerProtocol {}
 
-impl<TProtocols> Node<TProtocols> {
+impl<TProtocols> Node<TProtocols>
+where
+	TProtocols: crate::protocol::PeerProtocol,
+{
 	pub fn new(
 		peer_id: PeerId,
 		protocols: TProtocols,
 		transports: HashMap<Protocol, Boxed<(PeerId, StreamMuxerBox)>>,
+		executor: Option<Box<dyn crate::Executor + Send>>,
+		metrics: Option<Metrics>,
+		limits: ConnectionLimits,
 	) -> Self {
+		let mut peer_manager = match executor {
+			Some(executor) => manager::Manager::new().with_executor(executor),
+			None => manager::Manager::new(),
+		};
+		if let Some(metrics) = metrics.clone() {
+			peer_manager = peer_manager.with_metrics(metrics);
+		}
+		peer_manager = peer_manager.with_limits(limits);
+
 		Self {
 			peer_id,
 			transports,
 			protocols,
 			pending_events: VecDeque::new(),
-			peer_manager: manager::Manager::new(),
+			metrics,
+			shutting_down: false,
+			peer_manager,
 		}
 	}
 
+	/// Begins an orderly shutdown: stops accepting new connections and asks every established
+	/// connection to close. Keep polling this `Node` until its stream ends
+	/// ([`NodeEvent::ShutdownComplete`] is the last event it yields) to let connections drain.
+	pub fn shutdown(&mut self) {
+		self.peer_manager.start_shutdown();
+		self.shutting_down = true;
+	}
+
 	pub async fn dial(&mut self, remote_peer_id: PeerId, remote_address: Multiaddr) -> Result<(), Error> {
-		let connection_id = ConnectionId::next();
+		self.check_outgoing_limit()?;
+
+		let connection_id = self.peer_manager.next_connection_id();
 		info!(peer_id = %self.peer_id, %remote_peer_id, %remote_address, %connection_id, "Attempting to dial");
 
 		let protocol = extract_protocol_from_multiaddr(&remote_address)?;
@@ -66,6 +103,51 @@ impl<TProtocols> Node<TProtocols> {
 		Ok(())
 	}
 
+	/// Like [`Node::dial`], but for a coordinated hole-punch dial where both peers are dialing
+	/// each other at once (e.g. WebTransport NAT traversal). The negotiator runs the
+	/// `/libp2p/simultaneous-connect` role-arbitration handshake to decide which side actually
+	/// acts as the dialer. The resulting connection's [`ConnectionOrigin`] is recorded as
+	/// [`ConnectionOrigin::SimultaneousOpen`] when the dial is handed off; it does not record which
+	/// side the handshake picked as the dialer.
+	pub async fn dial_simultaneous_open(
+		&mut self,
+		remote_peer_id: PeerId,
+		local_address: Multiaddr,
+		remote_address: Multiaddr,
+	) -> Result<(), Error> {
+		self.check_outgoing_limit()?;
+
+		let connection_id = self.peer_manager.next_connection_id();
+		info!(peer_id = %self.peer_id, %remote_peer_id, %remote_address, %connection_id, "Attempting simultaneous-open dial");
+
+		let protocol = extract_protocol_from_multiaddr(&remote_address)?;
+
+		let transport = self.transports.get_mut(&protocol).ok_or_else(|| {
+			error!(peer_id = %self.peer_id, %remote_peer_id, %remote_address, ?protocol, "Transport not found for protocol");
+			Error::TransportNotFound(protocol)
+		})?;
+
+		let dial = match transport.dial(remote_address.clone()) {
+			Ok(fut) => fut.map_err(TransportError::Other).boxed(),
+			Err(e) => futures::future::ready(Result::<(PeerId, StreamMuxerBox), _>::Err(e)).boxed(),
+		};
+
+		self.peer_manager
+			.add_outgoing_simultaneous_open(dial, connection_id, local_address, remote_address);
+
+		Ok(())
+	}
+
+	/// Fails fast with [`Error::ImmediateDial`] if starting a new outbound dial right now would
+	/// exceed [`crate::peer::manager::ConnectionLimits::with_max_pending_outgoing`], rather than
+	/// letting the dial proceed and only learning of the denial asynchronously via
+	/// [`PeerEvent::ConnectionDenied`].
+	fn check_outgoing_limit(&self) -> Result<(), Error> {
+		self.peer_manager
+			.check_outgoing_limit()
+			.map_err(|(kind, current, limit)| Error::ImmediateDial(Box::new(Error::ConnectionLimit { kind, current, limit })))
+	}
+
 	pub async fn listen(&mut self, address: Multiaddr) -> Result<(), Error> {
 		let protocol = extract_protocol_from_multiaddr(&address)?;
 
@@ -84,7 +166,7 @@ impl<TProtocols> Node<TProtocols> {
 		Ok(())
 	}
 
-	fn poll_next_event(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<NodeEvent> {
+	fn poll_next_event(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<NodeEvent<TProtocols>> {
 		let this = &mut *self;
 
 		'outer: loop {
@@ -92,7 +174,12 @@ impl<TProtocols> Node<TProtocols> {
 				return Poll::Ready(event);
 			}
 
-			match this.peer_manager.poll(cx) {
+			let peer_manager_poll = if this.shutting_down {
+				this.peer_manager.poll_close(cx)
+			} else {
+				this.peer_manager.poll(cx)
+			};
+			match peer_manager_poll {
 				Poll::Pending => {}
 				Poll::Ready(event) => {
 					this.handle_peer_event(event);
@@ -100,6 +187,14 @@ impl<TProtocols> Node<TProtocols> {
 				}
 			}
 
+			match this.protocols.poll(cx) {
+				Poll::Pending => {}
+				Poll::Ready(action) => {
+					this.handle_protocol_action(action);
+					continue 'outer;
+				}
+			}
+
 			for v in this.transports.values_mut() {
 				match Pin::new(v).poll(cx) {
 					Poll::Ready(event) => {
@@ -115,8 +210,8 @@ impl<TProtocols> Node<TProtocols> {
 	}
 
 	#[inline]
-	fn handle_peer_event(&mut self, _event: PeerEvent) {
-		match _event {
+	fn handle_peer_event(&mut self, event: PeerEvent<crate::protocol::THandlerToEvent<TProtocols>>) {
+		match event {
 			PeerEvent::ConnectionEstablished {
 				connection_origin,
 				connection_id,
@@ -132,6 +227,47 @@ impl<TProtocols> Node<TProtocols> {
 			),
 			PeerEvent::PendingOutboundConnectionError { .. } => {}
 			PeerEvent::PendingInboundConnectionError { .. } => {}
+			PeerEvent::ConnectionDenied { connection_id, limit } => {
+				self.pending_events.push_back(NodeEvent::ConnectionDenied { connection_id, limit });
+			}
+			PeerEvent::ShutdownComplete => {
+				self.pending_events.push_back(NodeEvent::ShutdownComplete);
+			}
+			PeerEvent::Notification {
+				connection_id,
+				peer_id,
+				event,
+			} => {
+				self.pending_events.push_back(NodeEvent::Notification {
+					connection_id,
+					peer_id,
+					event,
+				});
+			}
+			PeerEvent::AddressChange {
+				connection_id,
+				peer_id,
+				new_address,
+			} => {
+				self.pending_events.push_back(NodeEvent::ConnectionAddressChanged {
+					connection_id,
+					peer_id,
+					new_address,
+				});
+			}
+			PeerEvent::ConnectionClosed {
+				connection_id,
+				peer_id,
+				error,
+				remaining_for_peer,
+			} => {
+				self.pending_events.push_back(NodeEvent::ConnectionClosed {
+					connection_id,
+					peer_id,
+					error,
+					remaining_for_peer,
+				});
+			}
 		}
 	}
 
@@ -145,10 +281,44 @@ impl<TProtocols> Node<TProtocols> {
 		stream_muxer_box: StreamMuxerBox,
 		established_in: web_time::Duration,
 	) {
+		let (remote_addr, local_addr) = match &connection_origin {
+			ConnectionOrigin::Dialer { remote_addr } => (remote_addr, None),
+			ConnectionOrigin::Listener { local_addr, remote_addr } => (remote_addr, Some(local_addr)),
+			ConnectionOrigin::SimultaneousOpen { local_addr, remote_addr } => (remote_addr, Some(local_addr)),
+		};
+
+		let handler = match self.protocols.on_new_connection(connection_id, peer_id, remote_addr, local_addr) {
+			Ok(handler) => handler,
+			Err(error) => {
+				tracing::debug!(%connection_id, %peer_id, ?error, "Protocol refused new connection");
+				self.peer_manager.deny_connection_id(connection_id);
+				return;
+			}
+		};
+
+		self.peer_manager
+			.spawn_connection(connection_id, peer_id, connection_origin, stream_muxer_box, handler);
+
 		let node_event = NodeEvent::ConnectionEstablished { connection_id, peer_id };
 		self.pending_events.push_back(node_event);
 	}
 
+	#[inline]
+	fn handle_protocol_action(&mut self, action: Action<TProtocols::ToNode, THandlerFromEvent<TProtocols>>) {
+		match action {
+			Action::Event(event) => self.pending_events.push_back(NodeEvent::Protocol(event)),
+			Action::Notify { target, event } => {
+				if !self.peer_manager.send_to_target(target, event) {
+					tracing::debug!(?target, "Dropping protocol notification: target has no established connection");
+				}
+			}
+			Action::Nothing => {}
+			other => {
+				tracing::warn!(?other, "PeerProtocol action not wired into Node yet");
+			}
+		}
+	}
+
 	#[inline]
 	fn handle_transport_event(&mut self, event: TransportEventBoxed) {
 		match event {
@@ -169,10 +339,20 @@ impl<TProtocols> Node<TProtocols> {
 	where
 		TFut: Future<Output = Result<(PeerId, StreamMuxerBox), std::io::Error>> + Send + 'static,
 	{
-		let connection_id = ConnectionId::next();
+		let connection_id = self.peer_manager.next_connection_id();
 		tracing::debug!(peer_id = %self.peer_id, %remote_addr, %connection_id, "Incoming connection");
-		self.peer_manager
-			.add_incoming(upgrade, connection_id, local_addr, remote_addr.clone());
+
+		if self.peer_manager.has_pending_dial_to(&remote_addr) {
+			// We're already dialing this address ourselves: this is a simultaneous-open race
+			// (e.g. a coordinated hole punch), so the negotiator needs to run role arbitration
+			// instead of assuming we're the listener.
+			tracing::debug!(peer_id = %self.peer_id, %remote_addr, %connection_id, "Incoming connection races our own dial, treating as simultaneous open");
+			self.peer_manager
+				.add_incoming_simultaneous_open(upgrade, connection_id, local_addr, remote_addr.clone());
+		} else {
+			self.peer_manager
+				.add_incoming(upgrade, connection_id, local_addr, remote_addr.clone());
+		}
 
 		let node_event = NodeEvent::IncomingConnection {
 			remote_address: remote_addr,
@@ -183,6 +363,9 @@ impl<TProtocols> Node<TProtocols> {
 	#[inline]
 	fn handle_transport_event_listen_address(&mut self, address: Multiaddr) {
 		tracing::debug!(peer_id = %self.peer_id, %address, "Listening on");
+		if let Some(metrics) = &self.metrics {
+			metrics.listen_addr_added();
+		}
 		let node_event = NodeEvent::NewListenAddr { address };
 		self.pending_events.push_back(node_event);
 	}
@@ -190,6 +373,9 @@ impl<TProtocols> Node<TProtocols> {
 	#[inline]
 	fn handle_transport_event_address_expired(&mut self, address: Multiaddr) {
 		tracing::debug!(peer_id = %self.peer_id, %address, "Listen address expired");
+		if let Some(metrics) = &self.metrics {
+			metrics.listen_addr_expired();
+		}
 		let node_event = NodeEvent::AddressExpired { address };
 		self.pending_events.push_back(node_event);
 	}
@@ -204,20 +390,29 @@ impl<TProtocols> Node<TProtocols> {
 	#[inline]
 	fn handle_transport_event_listener_error(&mut self, error: io::Error) {
 		tracing::debug!(peer_id = %self.peer_id, ?error, "Listener error");
+		if let Some(metrics) = &self.metrics {
+			metrics.listener_error();
+		}
 		let node_event = NodeEvent::ListenerError { error };
 		self.pending_events.push_back(node_event);
 	}
 }
 
-impl<TProtocols> futures::Stream for Node<TProtocols> {
-	type Item = NodeEvent;
+impl<TProtocols> futures::Stream for Node<TProtocols>
+where
+	TProtocols: crate::protocol::PeerProtocol,
+{
+	type Item = NodeEvent<TProtocols>;
 
 	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
 		self.poll_next_event(cx).map(Some)
 	}
 }
 
-impl<TProtocols> FusedStream for Node<TProtocols> {
+impl<TProtocols> FusedStream for Node<TProtocols>
+where
+	TProtocols: crate::protocol::PeerProtocol,
+{
 	fn is_terminated(&self) -> bool {
 		false
 	}
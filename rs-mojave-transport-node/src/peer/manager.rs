@@ -12,18 +12,21 @@ use rs_mojave_network_core::{
 	transport::TransportError,
 };
 use std::{
-	collections::HashMap,
+	collections::{HashMap, VecDeque},
 	convert::Infallible,
+	num::NonZeroU32,
 	task::{Context, Poll, Waker},
 };
 use tracing::Instrument;
 use web_time::Instant;
 
 use crate::{
-	ProtocolHandler,
-	connection::{Connection, ConnectionId},
+	ConnectionError, ProtocolHandler,
+	connection::{Connection, ConnectionId, ConnectionIdAllocator},
 	executor::{Executor, get_executor},
+	metrics::{self, Metrics},
 	peer::{PendingInboundConnectionError, PendingOutboundConnectionError, task},
+	protocol::NotifyTarget,
 };
 
 struct TaskExecutor(Box<dyn Executor + Send>);
@@ -33,6 +36,10 @@ impl TaskExecutor {
 		Self(Box::new(get_executor()))
 	}
 
+	pub fn with_executor(executor: Box<dyn Executor + Send>) -> Self {
+		Self(executor)
+	}
+
 	#[track_caller]
 	pub fn spawn(&self, future: impl Future<Output = ()> + Send + 'static) {
 		let future = future.boxed();
@@ -47,6 +54,81 @@ pub struct EstablishedConnection<TFromProtocol> {
 	sender: mpsc::Sender<task::Command<TFromProtocol>>,
 }
 
+/// A point-in-time snapshot of [`Manager`]'s connection counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionCounters {
+	pending_incoming: u32,
+	pending_outgoing: u32,
+	established: u32,
+}
+
+impl ConnectionCounters {
+	/// Number of pending incoming (listener) connections.
+	pub fn pending_incoming(&self) -> u32 {
+		self.pending_incoming
+	}
+
+	/// Number of pending outgoing (dialer) connections.
+	pub fn pending_outgoing(&self) -> u32 {
+		self.pending_outgoing
+	}
+
+	/// Total number of pending connections, regardless of origin.
+	pub fn pending(&self) -> u32 {
+		self.pending_incoming + self.pending_outgoing
+	}
+
+	/// Total number of established connections across all peers.
+	pub fn established(&self) -> u32 {
+		self.established
+	}
+}
+
+/// The kind of connection limit that was exceeded, reported via [`PeerEvent::ConnectionDenied`]
+/// and [`crate::error::Error::ConnectionLimit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionLimit {
+	PendingIncoming,
+	PendingOutgoing,
+	EstablishedTotal,
+	EstablishedPerPeer,
+}
+
+/// Caps on how many connections [`Manager`] will admit. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectionLimits {
+	max_pending_incoming: Option<u32>,
+	max_pending_outgoing: Option<u32>,
+	max_established_total: Option<u32>,
+	max_established_per_peer: Option<u32>,
+}
+
+impl ConnectionLimits {
+	/// Sets the maximum number of pending incoming connections.
+	pub fn with_max_pending_incoming(mut self, limit: Option<u32>) -> Self {
+		self.max_pending_incoming = limit;
+		self
+	}
+
+	/// Sets the maximum number of pending outgoing connections.
+	pub fn with_max_pending_outgoing(mut self, limit: Option<u32>) -> Self {
+		self.max_pending_outgoing = limit;
+		self
+	}
+
+	/// Sets the maximum number of established connections, across all peers.
+	pub fn with_max_established_total(mut self, limit: Option<u32>) -> Self {
+		self.max_established_total = limit;
+		self
+	}
+
+	/// Sets the maximum number of established connections per peer.
+	pub fn with_max_established_per_peer(mut self, limit: Option<u32>) -> Self {
+		self.max_established_per_peer = limit;
+		self
+	}
+}
+
 pub struct Manager<THandler>
 where
 	THandler: ProtocolHandler,
@@ -54,13 +136,26 @@ where
 	pending_peer_events_tx: mpsc::Sender<task::PendingPeerEvent>,
 	pending_peer_events_rx: mpsc::Receiver<task::PendingPeerEvent>,
 	new_peer_dropped_listeners: FuturesUnordered<oneshot::Receiver<StreamMuxerBox>>,
-	peer_events: SelectAll<mpsc::Receiver<task::PeerEvent>>,
+	peer_events: SelectAll<mpsc::Receiver<task::EstablishedConnectionEvent<THandler::ToProtocol>>>,
 	task_executor: TaskExecutor,
 	pending: HashMap<ConnectionId, PendingPeer>,
 	established: HashMap<PeerId, HashMap<ConnectionId, EstablishedConnection<THandler::FromProtocol>>>,
+	connection_ids: ConnectionIdAllocator,
 
 	// connections
 	no_established_connections_waker: Option<Waker>,
+
+	/// Set once [`Manager::start_shutdown`] has been called. While set, no new pending or
+	/// established connections are accepted.
+	shutting_down: bool,
+
+	limits: ConnectionLimits,
+	counters: ConnectionCounters,
+	/// Events that are ready as soon as they're produced (e.g. limit denials), delivered ahead
+	/// of anything sourced from the async channels in [`Manager::poll`].
+	immediate_events: VecDeque<PeerEvent<THandler::ToProtocol>>,
+
+	metrics: Option<Metrics>,
 }
 
 impl<THandler> Manager<THandler>
@@ -78,8 +173,68 @@ where
 			task_executor: TaskExecutor::new(),
 			pending: Default::default(),
 			established: Default::default(),
+			connection_ids: ConnectionIdAllocator::new(),
 			no_established_connections_waker: None,
+			shutting_down: false,
+			limits: ConnectionLimits::default(),
+			counters: ConnectionCounters::default(),
+			immediate_events: VecDeque::new(),
+			metrics: None,
+		}
+	}
+
+	/// Runs this manager's connection tasks on a custom [`Executor`] instead of the
+	/// process-default one returned by [`get_executor`]. Useful for a single-threaded/local
+	/// executor, a WASM spawner, or an executor that records spawn metrics.
+	pub fn with_executor(mut self, executor: Box<dyn Executor + Send>) -> Self {
+		self.task_executor = TaskExecutor::with_executor(executor);
+		self
+	}
+
+	/// Records connection/pending-peer metrics into `metrics` as they happen. See
+	/// [`crate::Builder::with_metrics`].
+	pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+		self.metrics = Some(metrics);
+		self
+	}
+
+	/// Sets the [`ConnectionLimits`] this manager enforces. Defaults to unlimited.
+	pub fn with_limits(mut self, limits: ConnectionLimits) -> Self {
+		self.limits = limits;
+		self
+	}
+
+	/// Returns a snapshot of the current connection counters.
+	pub fn counters(&self) -> ConnectionCounters {
+		self.counters
+	}
+
+	/// Number of established connections to `peer_id` right now -- the per-peer figure
+	/// [`ConnectionLimits::with_max_established_per_peer`] enforces but that [`ConnectionCounters`]
+	/// only reports in aggregate across every peer.
+	pub fn established_for_peer(&self, peer_id: &PeerId) -> u32 {
+		self.established.get(peer_id).map_or(0, |connections| connections.len() as u32)
+	}
+
+	/// Allocates a fresh [`ConnectionId`] from this manager's own [`ConnectionIdAllocator`],
+	/// rather than a process-wide global, so independent managers don't contend on or alias each
+	/// other's ids.
+	pub(crate) fn next_connection_id(&self) -> ConnectionId {
+		self.connection_ids.next()
+	}
+
+	/// Checks whether admitting one more pending outgoing connection would exceed
+	/// [`ConnectionLimits::with_max_pending_outgoing`], without mutating any state. Returns the
+	/// limit kind together with the current count and the configured limit on failure, so callers
+	/// like [`crate::Node::dial`] can fail fast with a precise
+	/// [`crate::error::Error::ConnectionLimit`] instead of waiting for an async denial.
+	pub(crate) fn check_outgoing_limit(&self) -> Result<(), (ConnectionLimit, u32, u32)> {
+		if let Some(limit) = self.limits.max_pending_outgoing {
+			if self.counters.pending_outgoing >= limit {
+				return Err((ConnectionLimit::PendingOutgoing, self.counters.pending_outgoing, limit));
+			}
 		}
+		Ok(())
 	}
 
 	pub(crate) fn add_incoming<TFut>(
@@ -91,6 +246,21 @@ where
 	) where
 		TFut: Future<Output = Result<(PeerId, StreamMuxerBox), std::io::Error>> + Send + 'static,
 	{
+		if self.shutting_down {
+			tracing::debug!(%connection_id, "Rejecting incoming connection: manager is shutting down");
+			return;
+		}
+
+		if self.limits.max_pending_incoming.is_some_and(|limit| self.counters.pending_incoming >= limit) {
+			tracing::debug!(%connection_id, "Denying incoming connection: pending-incoming limit reached");
+			self.connection_ids.remove(connection_id);
+			self.immediate_events.push_back(PeerEvent::ConnectionDenied {
+				connection_id,
+				limit: ConnectionLimit::PendingIncoming,
+			});
+			return;
+		}
+
 		let (abort_notifier, abort_receiver) = oneshot::channel();
 
 		let span = tracing::debug_span!(parent: tracing::Span::none(), "new_incoming_connection", remote_addr = %remote_addr, id = %local_addr);
@@ -106,6 +276,7 @@ where
 			.instrument(span),
 		);
 
+		self.counters.pending_incoming += 1;
 		self.pending.insert(
 			connection_id,
 			PendingPeer {
@@ -113,6 +284,73 @@ where
 					local_addr,
 					remote_addr,
 				},
+				counted_incoming: true,
+				abort_notifier: Some(abort_notifier),
+				accepted_at: Instant::now(),
+			},
+		);
+	}
+
+	/// Returns `true` if there's already a pending outbound dial to `remote_addr`, meaning an
+	/// inbound connection from that same address is racing it and should be negotiated as a
+	/// simultaneous open rather than a plain accepted connection.
+	pub(crate) fn has_pending_dial_to(&self, remote_addr: &Multiaddr) -> bool {
+		self.pending
+			.values()
+			.any(|pending| matches!(&pending.connection_origin, ConnectionOrigin::Dialer { remote_addr: addr } if addr == remote_addr))
+	}
+
+	/// Like [`Manager::add_incoming`], but records the connection's origin as
+	/// [`ConnectionOrigin::SimultaneousOpen`] because it was accepted while we also had a pending
+	/// outbound dial to the same address (see [`Manager::has_pending_dial_to`]).
+	pub(crate) fn add_incoming_simultaneous_open<TFut>(
+		&mut self,
+		upgrade: TFut,
+		connection_id: ConnectionId,
+		local_addr: Multiaddr,
+		remote_addr: Multiaddr,
+	) where
+		TFut: Future<Output = Result<(PeerId, StreamMuxerBox), std::io::Error>> + Send + 'static,
+	{
+		if self.shutting_down {
+			tracing::debug!(%connection_id, "Rejecting incoming connection: manager is shutting down");
+			return;
+		}
+
+		if self.limits.max_pending_incoming.is_some_and(|limit| self.counters.pending_incoming >= limit) {
+			tracing::debug!(%connection_id, "Denying incoming connection: pending-incoming limit reached");
+			self.connection_ids.remove(connection_id);
+			self.immediate_events.push_back(PeerEvent::ConnectionDenied {
+				connection_id,
+				limit: ConnectionLimit::PendingIncoming,
+			});
+			return;
+		}
+
+		let (abort_notifier, abort_receiver) = oneshot::channel();
+
+		let span = tracing::debug_span!(parent: tracing::Span::none(), "new_simultaneous_open_connection", remote_addr = %remote_addr, id = %local_addr);
+		span.follows_from(tracing::Span::current());
+
+		self.task_executor.spawn(
+			task::new_pending_inbound_peer(
+				upgrade,
+				connection_id,
+				abort_receiver,
+				self.pending_peer_events_tx.clone(),
+			)
+			.instrument(span),
+		);
+
+		self.counters.pending_incoming += 1;
+		self.pending.insert(
+			connection_id,
+			PendingPeer {
+				connection_origin: ConnectionOrigin::SimultaneousOpen {
+					local_addr,
+					remote_addr,
+				},
+				counted_incoming: true,
 				abort_notifier: Some(abort_notifier),
 				accepted_at: Instant::now(),
 			},
@@ -125,6 +363,18 @@ where
 		connection_id: ConnectionId,
 		remote_addr: Multiaddr,
 	) {
+		if self.shutting_down {
+			tracing::debug!(%connection_id, "Rejecting outgoing connection: manager is shutting down");
+			return;
+		}
+
+		if let Err((limit, current, limit_value)) = self.check_outgoing_limit() {
+			tracing::debug!(%connection_id, ?limit, current, limit_value, "Denying outgoing connection: limit reached");
+			self.connection_ids.remove(connection_id);
+			self.immediate_events.push_back(PeerEvent::ConnectionDenied { connection_id, limit });
+			return;
+		}
+
 		let (abort_notifier, abort_receiver) = oneshot::channel();
 
 		let span =
@@ -136,16 +386,76 @@ where
 				.instrument(span),
 		);
 
+		self.counters.pending_outgoing += 1;
 		self.pending.insert(
 			connection_id,
 			PendingPeer {
 				connection_origin: ConnectionOrigin::Dialer { remote_addr },
+				counted_incoming: false,
+				abort_notifier: Some(abort_notifier),
+				accepted_at: Instant::now(),
+			},
+		);
+	}
+
+	/// Like [`Manager::add_outgoing`], but records the connection's origin as
+	/// [`ConnectionOrigin::SimultaneousOpen`] for a coordinated hole-punch dial where both peers
+	/// are dialing each other at once (e.g. WebTransport NAT traversal), rather than a plain
+	/// dial. The negotiator runs the `/libp2p/simultaneous-connect` role-arbitration handshake
+	/// (see `connection::negotiator::Version::V1SimOpen`) to decide which side actually acts as
+	/// the dialer.
+	pub(crate) fn add_outgoing_simultaneous_open(
+		&mut self,
+		dial: BoxFuture<'static, Result<(PeerId, StreamMuxerBox), TransportError<std::io::Error>>>,
+		connection_id: ConnectionId,
+		local_addr: Multiaddr,
+		remote_addr: Multiaddr,
+	) {
+		if self.shutting_down {
+			tracing::debug!(%connection_id, "Rejecting outgoing connection: manager is shutting down");
+			return;
+		}
+
+		if let Err((limit, current, limit_value)) = self.check_outgoing_limit() {
+			tracing::debug!(%connection_id, ?limit, current, limit_value, "Denying outgoing connection: limit reached");
+			self.connection_ids.remove(connection_id);
+			self.immediate_events.push_back(PeerEvent::ConnectionDenied { connection_id, limit });
+			return;
+		}
+
+		let (abort_notifier, abort_receiver) = oneshot::channel();
+
+		let span = tracing::debug_span!(parent: tracing::Span::none(), "new_simultaneous_open_connection", remote_addr = %remote_addr);
+		span.follows_from(tracing::Span::current());
+
+		self.task_executor.spawn(
+			task::new_pending_outgoing_peer(dial, connection_id, abort_receiver, self.pending_peer_events_tx.clone())
+				.instrument(span),
+		);
+
+		self.counters.pending_outgoing += 1;
+		self.pending.insert(
+			connection_id,
+			PendingPeer {
+				connection_origin: ConnectionOrigin::SimultaneousOpen {
+					local_addr,
+					remote_addr,
+				},
+				counted_incoming: false,
 				abort_notifier: Some(abort_notifier),
 				accepted_at: Instant::now(),
 			},
 		);
 	}
 
+	/// Releases `connection_id` back to the allocator for a connection that reached the
+	/// established stage at the transport level but was refused before [`Manager::spawn_connection`]
+	/// was ever called -- e.g. the node's [`crate::protocol::PeerProtocol::on_new_connection`]
+	/// returned an error.
+	pub(crate) fn deny_connection_id(&mut self, connection_id: ConnectionId) {
+		self.connection_ids.remove(connection_id);
+	}
+
 	pub(crate) fn spawn_connection(
 		&mut self,
 		connection_id: ConnectionId,
@@ -154,6 +464,32 @@ where
 		connection: StreamMuxerBox,
 		protocol: THandler,
 	) {
+		if self.shutting_down {
+			tracing::debug!(%connection_id, %peer_id, "Dropping newly established connection: manager is shutting down");
+			return;
+		}
+
+		if self.limits.max_established_total.is_some_and(|limit| self.counters.established >= limit) {
+			tracing::debug!(%connection_id, %peer_id, "Denying established connection: established-total limit reached");
+			self.connection_ids.remove(connection_id);
+			self.immediate_events.push_back(PeerEvent::ConnectionDenied {
+				connection_id,
+				limit: ConnectionLimit::EstablishedTotal,
+			});
+			return;
+		}
+
+		let established_for_peer = self.established.get(&peer_id).map_or(0, |connections| connections.len() as u32);
+		if self.limits.max_established_per_peer.is_some_and(|limit| established_for_peer >= limit) {
+			tracing::debug!(%connection_id, %peer_id, "Denying established connection: established-per-peer limit reached");
+			self.connection_ids.remove(connection_id);
+			self.immediate_events.push_back(PeerEvent::ConnectionDenied {
+				connection_id,
+				limit: ConnectionLimit::EstablishedPerPeer,
+			});
+			return;
+		}
+
 		let connections = self.established.entry(peer_id).or_default();
 
 		// TODO: replace with config vars
@@ -166,6 +502,8 @@ where
 			sender: command_sender,
 		};
 		connections.insert(connection_id, established_connection);
+		self.counters.established += 1;
+		self.peer_events.push(event_receiver);
 		let connection = Connection::new(protocol, connection);
 
 		let span = tracing::debug_span!(parent: tracing::Span::none(), "new_established_connection", %connection_id, peer = %peer_id);
@@ -180,7 +518,105 @@ where
 		));
 	}
 
-	pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<PeerEvent> {
+	/// Removes a connection's bookkeeping once its task has fully torn down, decrementing
+	/// [`ConnectionCounters::established`]. Returns the number of connections still established
+	/// with `peer_id` after the removal.
+	pub(crate) fn remove_established(&mut self, peer_id: PeerId, connection_id: ConnectionId) -> u32 {
+		let Some(connections) = self.established.get_mut(&peer_id) else {
+			return 0;
+		};
+
+		if connections.remove(&connection_id).is_none() {
+			return connections.len() as u32;
+		}
+
+		self.counters.established = self.counters.established.saturating_sub(1);
+
+		let remaining = connections.len() as u32;
+		if connections.is_empty() {
+			self.established.remove(&peer_id);
+		}
+
+		remaining
+	}
+
+	/// Delivers `msg` to the protocol handler of the established connection `connection_id`
+	/// owned by `peer_id`, the symmetric counterpart to the `Notify` events surfaced by
+	/// [`Manager::poll`] as [`PeerEvent::Notification`]. Returns `false` if there's no such
+	/// established connection, or if its task has already stopped accepting commands.
+	pub(crate) fn send_to_handler(
+		&mut self,
+		peer_id: PeerId,
+		connection_id: ConnectionId,
+		msg: THandler::FromProtocol,
+	) -> bool {
+		let Some(established) = self
+			.established
+			.get_mut(&peer_id)
+			.and_then(|connections| connections.get_mut(&connection_id))
+		else {
+			return false;
+		};
+
+		established.sender.try_send(task::Command::NotifyProtocol(msg)).is_ok()
+	}
+
+	/// Resolves `target` to an established connection and delivers `msg` via
+	/// [`Manager::send_to_handler`]. [`NotifyTarget::Peer`] picks an arbitrary established
+	/// connection of `peer_id`'s -- fine for callers that only ever address by peer and never
+	/// send more than one command at a time per peer (e.g. a request/response exchange). Returns
+	/// `false` if `target` has no established connection anymore.
+	pub(crate) fn send_to_target(&mut self, target: NotifyTarget, msg: THandler::FromProtocol) -> bool {
+		let (peer_id, connection_id) = match target {
+			NotifyTarget::Connection(peer_id, connection_id) => (peer_id, connection_id),
+			NotifyTarget::Peer(peer_id) => {
+				let Some(connection_id) = self.established.get(&peer_id).and_then(|connections| connections.keys().next()).copied() else {
+					return false;
+				};
+				(peer_id, connection_id)
+			}
+		};
+
+		self.send_to_handler(peer_id, connection_id, msg)
+	}
+
+	/// Begins an orderly shutdown: stops accepting new connections, aborts everything still
+	/// pending, and asks every established connection task to close. Callers must keep polling
+	/// [`Manager::poll_close`] until it yields [`PeerEvent::ShutdownComplete`] to let connections drain.
+	pub(crate) fn start_shutdown(&mut self) {
+		if self.shutting_down {
+			return;
+		}
+		self.shutting_down = true;
+
+		for (_, pending) in self.pending.iter_mut() {
+			pending.abort();
+		}
+
+		for connections in self.established.values_mut() {
+			for established in connections.values_mut() {
+				let _ = established.sender.try_send(task::Command::Close);
+			}
+		}
+	}
+
+	/// Drives the shutdown started by [`Manager::start_shutdown`] to completion, yielding
+	/// [`PeerEvent::ShutdownComplete`] once every pending and established connection has gone away.
+	pub(crate) fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<PeerEvent<THandler::ToProtocol>> {
+		debug_assert!(self.shutting_down, "poll_close called without start_shutdown");
+
+		if self.pending.is_empty() && self.established.is_empty() {
+			return Poll::Ready(PeerEvent::ShutdownComplete);
+		}
+
+		self.poll(cx)
+	}
+
+	pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<PeerEvent<THandler::ToProtocol>> {
+		if let Some(event) = self.immediate_events.pop_front() {
+			return Poll::Ready(event);
+		}
+
 		match self.peer_events.poll_next_unpin(cx) {
 			Poll::Pending => {}
 			Poll::Ready(None) => {
@@ -218,12 +654,55 @@ where
 	}
 
 	#[inline]
-	fn handle_peer_event(&mut self, _event: task::PeerEvent) -> Poll<PeerEvent> {
-		todo!()
+	fn handle_peer_event(
+		&mut self,
+		event: task::EstablishedConnectionEvent<THandler::ToProtocol>,
+	) -> Poll<PeerEvent<THandler::ToProtocol>> {
+		match event {
+			task::EstablishedConnectionEvent::Notify {
+				connection_id,
+				peer_id,
+				event,
+			} => Poll::Ready(PeerEvent::Notification {
+				connection_id,
+				peer_id,
+				event,
+			}),
+			task::EstablishedConnectionEvent::AddressChange {
+				connection_id,
+				peer_id,
+				new_address,
+			} => {
+				if let Some(metrics) = &self.metrics {
+					metrics.address_change();
+				}
+				Poll::Ready(PeerEvent::AddressChange {
+					connection_id,
+					peer_id,
+					new_address,
+				})
+			}
+			task::EstablishedConnectionEvent::Closed {
+				connection_id,
+				peer_id,
+				error,
+			} => {
+				if let Some(metrics) = &self.metrics {
+					metrics.connection_closed(error.as_ref());
+				}
+				let remaining_for_peer = NonZeroU32::new(self.remove_established(peer_id, connection_id));
+				Poll::Ready(PeerEvent::ConnectionClosed {
+					connection_id,
+					peer_id,
+					error,
+					remaining_for_peer,
+				})
+			}
+		}
 	}
 
 	#[inline]
-	fn handle_pending_peer_event(&mut self, event: task::PendingPeerEvent) -> Poll<PeerEvent> {
+	fn handle_pending_peer_event(&mut self, event: task::PendingPeerEvent) -> Poll<PeerEvent<THandler::ToProtocol>> {
 		match event {
 			task::PendingPeerEvent::ConnectionEstablished { connection_id, output } => {
 				self.handle_pending_peer_event_connection_established(connection_id, output)
@@ -239,9 +718,10 @@ where
 		&mut self,
 		connection_id: ConnectionId,
 		output: (PeerId, StreamMuxerBox),
-	) -> Poll<PeerEvent> {
+	) -> Poll<PeerEvent<THandler::ToProtocol>> {
 		let PendingPeer {
 			connection_origin,
+			counted_incoming,
 			abort_notifier: _,
 			accepted_at,
 		} = self
@@ -249,10 +729,21 @@ where
 			.remove(&connection_id)
 			.expect("Entry in `self.pending` not found for pending peer");
 
+		if counted_incoming {
+			self.counters.pending_incoming -= 1;
+		} else {
+			self.counters.pending_outgoing -= 1;
+		}
+
 		let (peer_id, stream_muxer_box) = output;
 
 		let established_in = accepted_at.elapsed();
 
+		if let Some(metrics) = &self.metrics {
+			let direction = if counted_incoming { metrics::Direction::Inbound } else { metrics::Direction::Outbound };
+			metrics.pending_established(direction);
+		}
+
 		Poll::Ready(PeerEvent::ConnectionEstablished {
 			connection_origin,
 			connection_id,
@@ -267,19 +758,31 @@ where
 		&mut self,
 		connection_id: ConnectionId,
 		error: Either<PendingOutboundConnectionError, PendingInboundConnectionError>,
-	) -> Poll<PeerEvent> {
+	) -> Poll<PeerEvent<THandler::ToProtocol>> {
 		self.pending.remove(&connection_id);
 		// connection is lost at that point, so we remove it from the ConnectionId registry
-		ConnectionId::remove(connection_id);
+		self.connection_ids.remove(connection_id);
 		match error {
-			Either::Left(error) => Poll::Ready(PeerEvent::PendingOutboundConnectionError { connection_id, error }),
-			Either::Right(error) => Poll::Ready(PeerEvent::PendingInboundConnectionError { connection_id, error }),
+			Either::Left(error) => {
+				self.counters.pending_outgoing -= 1;
+				if let Some(metrics) = &self.metrics {
+					metrics.pending_failed_outbound(&error);
+				}
+				Poll::Ready(PeerEvent::PendingOutboundConnectionError { connection_id, error })
+			}
+			Either::Right(error) => {
+				self.counters.pending_incoming -= 1;
+				if let Some(metrics) = &self.metrics {
+					metrics.pending_failed_inbound(&error);
+				}
+				Poll::Ready(PeerEvent::PendingInboundConnectionError { connection_id, error })
+			}
 		}
 	}
 }
 
 #[derive(Debug)]
-pub(crate) enum PeerEvent {
+pub(crate) enum PeerEvent<TToProtocol> {
 	PendingOutboundConnectionError {
 		connection_id: ConnectionId,
 		error: PendingOutboundConnectionError,
@@ -296,11 +799,53 @@ pub(crate) enum PeerEvent {
 		stream_muxer_box: StreamMuxerBox,
 		established_in: web_time::Duration,
 	},
+
+	/// Yielded once by [`Manager::poll_close`] once the drain started by
+	/// [`Manager::start_shutdown`] has finished.
+	ShutdownComplete,
+
+	/// A connection was refused because it would have exceeded a configured [`ConnectionLimits`].
+	ConnectionDenied {
+		connection_id: ConnectionId,
+		limit: ConnectionLimit,
+	},
+
+	/// A protocol handler produced an event for its established connection. The symmetric
+	/// counterpart to [`Manager::send_to_handler`].
+	Notification {
+		connection_id: ConnectionId,
+		peer_id: PeerId,
+		event: TToProtocol,
+	},
+
+	/// The observed remote address of an established connection changed.
+	AddressChange {
+		connection_id: ConnectionId,
+		peer_id: PeerId,
+		new_address: Multiaddr,
+	},
+
+	/// An established connection was closed, either by the remote, by [`Manager::start_shutdown`],
+	/// or because of a connection-level error.
+	ConnectionClosed {
+		connection_id: ConnectionId,
+		peer_id: PeerId,
+		error: Option<ConnectionError>,
+		/// How many other connections to the same peer are still established. `None` means this
+		/// was the last one -- useful for "last connection to peer dropped" logic.
+		remaining_for_peer: Option<NonZeroU32>,
+	},
 }
 
 struct PendingPeer {
 	connection_origin: ConnectionOrigin,
 
+	/// Which of [`ConnectionCounters::pending_incoming`]/[`ConnectionCounters::pending_outgoing`]
+	/// this entry was counted under. Tracked separately from `connection_origin` because a
+	/// [`ConnectionOrigin::SimultaneousOpen`] pending connection can originate from either side
+	/// of a hole-punch race (a dial we initiated, or an inbound connection we accepted).
+	counted_incoming: bool,
+
 	/// When dropped, notifies the task which then knows to terminate.
 	abort_notifier: Option<oneshot::Sender<Infallible>>,
 	/// The moment we became aware of this possible connection, useful for timing metrics.
@@ -25,8 +25,6 @@ pub(crate) enum PendingPeerEvent {
 	},
 }
 
-pub(crate) enum PeerEvent {}
-
 pub(crate) async fn new_pending_outgoing_peer<TFut>(
 	future: TFut,
 	connection_id: ConnectionId,
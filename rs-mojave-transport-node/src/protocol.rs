@@ -7,7 +7,7 @@ use multiaddr::{Multiaddr, PeerId};
 use thiserror::Error;
 
 use crate::{
-	AsyncReadWrite, ConnectionError, StreamProtocol,
+	AsyncReadWrite, ConnectionError, StreamProtocol, StreamProtocolMatcher,
 	connection::{self, ConnectionId},
 	stream_id::StreamId,
 };
@@ -34,16 +34,30 @@ pub enum ProtocolHandlerEvent<TEvent> {
 }
 
 pub enum ConnectionEvent {
-	NewInboundStream(Box<dyn AsyncReadWrite + Send + Unpin>),
-	NewOutboundStream(Box<dyn AsyncReadWrite + Send + Unpin>),
+	/// A new inbound substream, negotiated to the given concrete [`StreamProtocol`] (the first
+	/// protocol, in the remote's proposal order, that satisfies our [`ProtocolInfo`]).
+	NewInboundStream(StreamProtocol, Box<dyn AsyncReadWrite + Send + Unpin>),
+	/// A new outbound substream, negotiated to the given concrete [`StreamProtocol`].
+	NewOutboundStream(StreamProtocol, Box<dyn AsyncReadWrite + Send + Unpin>),
 	FailNegotiation(connection::negotiator::NegotiatorStreamError),
 	AddressChange(Multiaddr),
 }
 
+/// What a [`ProtocolHandler`] advertises via [`ProtocolHandler::protocol_info`]: either an exact
+/// protocol string, or a [`StreamProtocolMatcher`] accepting a range of versions. Negotiation
+/// matches a remote's advertised concrete protocols against whichever of these this handler
+/// offers, picking the first one in the dialer's proposal order that is satisfied (see
+/// [`StreamProtocolMatcher::matches`]).
+#[derive(Debug, Clone)]
+pub enum ProtocolInfo {
+	Exact(StreamProtocol),
+	Range(StreamProtocolMatcher),
+}
+
 pub trait ProtocolHandler: Send + 'static {
 	type FromProtocol: fmt::Debug + Send + 'static;
 	type ToProtocol: fmt::Debug + Send + 'static;
-	type ProtocolInfoIter: IntoIterator<Item = StreamProtocol>;
+	type ProtocolInfoIter: IntoIterator<Item = ProtocolInfo>;
 
 	fn protocol_info(&self) -> Self::ProtocolInfoIter;
 
@@ -61,7 +75,9 @@ pub trait ProtocolHandler: Send + 'static {
 }
 
 pub trait PeerProtocol: Send + 'static {
-	type ToNode: Send + 'static;
+	/// Must implement [`fmt::Debug`] so the hand-written `Debug` impl of [`crate::NodeEvent`] can
+	/// format [`crate::NodeEvent::Protocol`] -- the `Node`-level wrapper around [`Action::Event`].
+	type ToNode: fmt::Debug + Send + 'static;
 
 	type Handler: ProtocolHandler;
 
@@ -79,6 +95,17 @@ pub trait PeerProtocol: Send + 'static {
 	fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Action<Self::ToNode, THandlerFromEvent<Self>>>;
 }
 
+/// Where an [`Action::Notify`] event should be delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyTarget {
+	/// Any one established connection to `PeerId`. This is what a protocol addressing by peer
+	/// alone (e.g. [`PeerProtocol::on_new_connection`] callers that never learn a
+	/// [`ConnectionId`]) should use.
+	Peer(PeerId),
+	/// A single established connection, identified by both its peer and [`ConnectionId`].
+	Connection(PeerId, ConnectionId),
+}
+
 #[derive(Debug)]
 pub enum Action<TEvent, THandlerEvent> {
 	Event(TEvent),
@@ -89,7 +116,13 @@ pub enum Action<TEvent, THandlerEvent> {
 		stream: StreamId,
 		data: Vec<u8>,
 	},
-	Notify(THandlerEvent),
+	/// Delivers `event` to the [`ProtocolHandler`] at `target` via
+	/// [`crate::peer::manager::Manager::send_to_handler`]. Silently dropped if `target` no longer
+	/// has an established connection by the time [`crate::Node`] gets to it.
+	Notify {
+		target: NotifyTarget,
+		event: THandlerEvent,
+	},
 	CloseStream {
 		peer: PeerId,
 		stream: StreamId,
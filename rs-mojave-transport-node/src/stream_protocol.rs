@@ -1,4 +1,4 @@
-use semver::Version;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
 	fmt::{Debug, Display},
@@ -120,6 +120,46 @@ impl<'de> Deserialize<'de> for StreamProtocol {
 	}
 }
 
+/// Matches a family of [`StreamProtocol`]s sharing a namespace and name, accepting any version
+/// satisfying a [`VersionReq`] (e.g. `^1.2`) instead of pinning one exact [`Version`].
+///
+/// A handler advertising an exact `StreamProtocol` can never talk to a peer on a different
+/// version of the same protocol, even when the change is backward-compatible. Negotiation uses
+/// [`StreamProtocolMatcher::matches`] to accept any version a remote proposes that satisfies the
+/// range, so protocols can evolve without forking their name; [`StreamProtocolMatcher::best_match`]
+/// picks the highest satisfying version from a list of candidates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamProtocolMatcher {
+	pub namespace: String,
+	pub name: String,
+	pub version_req: VersionReq,
+}
+
+impl StreamProtocolMatcher {
+	/// Creates a matcher accepting any version of `namespace/name` satisfying `version_req`.
+	pub fn new(namespace: &str, name: &str, version_req: VersionReq) -> Self {
+		Self {
+			namespace: namespace.to_owned(),
+			name: name.to_owned(),
+			version_req,
+		}
+	}
+
+	/// Returns `true` if `candidate` shares this matcher's namespace and name, and its version
+	/// satisfies [`StreamProtocolMatcher::version_req`].
+	pub fn matches(&self, candidate: &StreamProtocol) -> bool {
+		self.namespace == candidate.namespace && self.name == candidate.name && self.version_req.matches(&candidate.version)
+	}
+
+	/// Picks the highest-versioned protocol in `candidates` that [`StreamProtocolMatcher::matches`].
+	pub fn best_match<'a>(&self, candidates: impl IntoIterator<Item = &'a StreamProtocol>) -> Option<&'a StreamProtocol> {
+		candidates
+			.into_iter()
+			.filter(|candidate| self.matches(candidate))
+			.max_by(|a, b| a.version.cmp(&b.version))
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -311,4 +351,44 @@ mod tests {
 
 		assert_eq!(original, deserialized);
 	}
+
+	#[test]
+	fn test_matcher_matches_version_in_range() {
+		let matcher = StreamProtocolMatcher::new("test", "protocol", VersionReq::parse("^1.2").unwrap());
+
+		let in_range = StreamProtocol::new("test", "protocol", Version::parse("1.2.5").unwrap());
+		let below_range = StreamProtocol::new("test", "protocol", Version::parse("1.1.0").unwrap());
+		let above_major = StreamProtocol::new("test", "protocol", Version::parse("2.0.0").unwrap());
+		let wrong_name = StreamProtocol::new("test", "other", Version::parse("1.2.5").unwrap());
+		let wrong_namespace = StreamProtocol::new("other", "protocol", Version::parse("1.2.5").unwrap());
+
+		assert!(matcher.matches(&in_range));
+		assert!(!matcher.matches(&below_range));
+		assert!(!matcher.matches(&above_major));
+		assert!(!matcher.matches(&wrong_name));
+		assert!(!matcher.matches(&wrong_namespace));
+	}
+
+	#[test]
+	fn test_matcher_best_match_picks_highest_satisfying_version() {
+		let matcher = StreamProtocolMatcher::new("test", "protocol", VersionReq::parse("^1").unwrap());
+
+		let candidates = vec![
+			StreamProtocol::new("test", "protocol", Version::parse("1.0.0").unwrap()),
+			StreamProtocol::new("test", "protocol", Version::parse("1.3.0").unwrap()),
+			StreamProtocol::new("test", "protocol", Version::parse("2.0.0").unwrap()),
+			StreamProtocol::new("test", "other", Version::parse("1.9.0").unwrap()),
+		];
+
+		let best = matcher.best_match(&candidates).unwrap();
+		assert_eq!(best.version, Version::parse("1.3.0").unwrap());
+	}
+
+	#[test]
+	fn test_matcher_best_match_none_when_nothing_satisfies() {
+		let matcher = StreamProtocolMatcher::new("test", "protocol", VersionReq::parse("^2").unwrap());
+		let candidates = vec![StreamProtocol::new("test", "protocol", Version::parse("1.0.0").unwrap())];
+
+		assert!(matcher.best_match(&candidates).is_none());
+	}
 }